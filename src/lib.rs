@@ -10,6 +10,9 @@
 //!
 //! Function name is last word in url.
 //!
+//! Data of request (headers, peer address, authenticated caller)
+//! is available in function via RequestContext::current().
+//!
 //! Signature functions:
 //!
 //!  fn(Handle, Arc<T>, Option<&str>) -> RasResult
@@ -103,6 +106,27 @@
 
 /// Additional functions
 pub mod ras_helper;
+/// Configuration of service from file (json, toml, yaml)
+/// and environment variables RAS_*.
+pub mod ras_config;
+/// Limits of concurrent connections and requests (load shedding).
+pub mod ras_concurrency;
+/// Rate limiting of requests (GCRA) by peer address, X-Forwarded-For or user.
+pub mod ras_rate_limit;
+/// Metrics of service and handlers in Prometheus text exposition format.
+pub mod ras_metrics;
+/// Liveness and readiness probes, graceful shutdown.
+pub mod ras_health;
+/// Propagation of W3C Trace Context and export of server spans.
+pub mod ras_trace;
+/// Id of request (header X-Request-Id) for correlation of logs.
+pub mod ras_request_id;
+/// Cross-origin resource sharing (preflight and headers of responses).
+pub mod ras_cors;
+/// Compression of responses and decompression of requests
+/// (features Gzip, Deflate, Brotli, Zstd).
+#[cfg(any(feature = "Gzip", feature = "Deflate", feature = "Brotli", feature = "Zstd"))]
+pub mod ras_compression;
 /// Tools for implementation of identification and authentication.
 ///
 /// For use your service must implementation trait RasAuthClient.
@@ -111,10 +135,67 @@ pub mod ras_helper;
 /// Authentication Server - ras_auth 
 #[cfg(feature = "Authentication")]
 pub mod ras_auth_client;
+/// Store of public keys for token with rotation.
+///
+/// Keys are identified by key id and refreshed from ras_auth.
+#[cfg(feature = "Authentication")]
+pub mod ras_key_store;
+/// JSON Web Keys (JWKS) and verification of JWT.
+///
+/// Allows trust tokens from OIDC-compatible issuers.
+#[cfg(feature = "Authentication")]
+pub mod ras_jwks;
+/// Deny list for revocation of access tokens.
+///
+/// Is updated by admin endpoint (also used for push from ras_auth).
+#[cfg(feature = "Authentication")]
+pub mod ras_deny_list;
+/// Issuance of tokens, signed by private key.
+///
+/// Tokens are created in format json@@sign (checked by RasAuthClient) or JWT.
+#[cfg(feature = "Authentication")]
+pub mod ras_token_signer;
+/// Client for calls to other services with role Service.
+///
+/// Access token is cached and renewed before end of its life time.
+#[cfg(feature = "Authentication")]
+pub mod ras_service_client;
+/// Auth layer: authenticators and requirements of routes.
+///
+/// Authenticated caller (Principal) is available in RequestContext.
+#[cfg(feature = "Authentication")]
+pub mod ras_auth_layer;
+/// Authentication by API keys (for cron jobs, partner webhooks).
+///
+/// Keys are stored hashed and mapped to Principal.
+#[cfg(feature = "Authentication")]
+pub mod ras_api_key;
+/// HTTP Basic authentication (for internal admin endpoints).
+///
+/// Passwords are checked by argon2 (feature "Argon2") or bcrypt (feature "Bcrypt") hashes.
+#[cfg(feature = "Authentication")]
+pub mod ras_basic_auth;
+/// TLS on listener, also certificates of clients (mutual TLS).
+///
+/// Certificate of client is available in RequestContext
+/// and is used by ClientCertAuthenticator (with feature "Authentication").
+#[cfg(feature = "Tls")]
+pub mod ras_tls;
+/// HTTP/2 connections: h2c with prior knowledge and ALPN "h2" over TLS.
+///
+/// Streams are dispatched to the same GET and POST functions.
+#[cfg(feature = "Http2")]
+pub mod ras_http2;
+/// Backend on hyper instead of own parsing of HTTP
+/// (keep-alive, chunked body, HTTP/2).
+///
+/// Functions are registered by the same RasServiceBuilder.
+#[cfg(feature = "Hyper")]
+pub mod ras_hyper;
 
 use tokio::{
 	task::JoinHandle,
-	io::AsyncWriteExt
+	io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt},
 };
 
 //re export
@@ -130,6 +211,71 @@ pub use std::{
 	sync::{Arc, Mutex},
 	collections::HashMap,
 };
+use std::time::Duration;
+use tokio::sync::OwnedSemaphorePermit;
+use ras_concurrency::{ConcurrencyLimit, ConcurrencyLimiter};
+use ras_rate_limit::{RateLimitDecision, RateLimiter};
+#[cfg(feature = "Authentication")]
+use ras_auth_layer::{Authenticator, AuthRequirement, Principal};
+
+tokio::task_local! {
+	static REQUEST_CONTEXT: Arc<RequestContext>;
+}
+
+/// Data of current request.
+///
+/// Is available in user function via RequestContext::current()
+/// (call it in function body, not in spawned task).
+#[derive(Debug, Clone, Default)]
+pub struct RequestContext {
+	pub method: String,
+	/// Decoded path with parameters
+	pub path: String,
+	pub func_name: String,
+	/// GET-parameters
+	pub params: Option<String>,
+	pub headers: Vec<(String, String)>,
+	pub peer_addr: Option<std::net::SocketAddr>,
+	/// Id from header X-Request-Id or generated, is returned in response
+	pub request_id: String,
+	/// Context of server span (use get_traceparent for calls to other services)
+	pub trace_context: Option<ras_trace::TraceContext>,
+	/// Certificate of client (mutual TLS)
+	#[cfg(feature = "Tls")]
+	pub peer_certificate: Option<ras_tls::PeerCertificate>,
+	/// Authenticated caller
+	#[cfg(feature = "Authentication")]
+	pub principal: Option<Principal>,
+}
+
+impl RequestContext {
+	/// Context of current request
+	pub fn current() -> Option<Arc<RequestContext>> {
+		REQUEST_CONTEXT.try_with(|context| context.clone()).ok()
+	}
+
+	/// Value of header (name is case insensitive)
+	pub fn header(&self, name: &str) -> Option<&str> {
+		self.headers
+			.iter()
+			.find(|(header_name, _)| header_name.eq_ignore_ascii_case(name))
+			.map(|(_, value)| value.as_str())
+	}
+
+	/// Headers for calls to other services (X-Request-Id, traceparent, tracestate)
+	pub fn get_propagation_headers(&self) -> Vec<(String, String)> {
+		let mut headers = vec![
+			(ras_request_id::REQUEST_ID_HEADER.to_string(), self.request_id.clone()),
+		];
+		if let Some(ref trace_context) = self.trace_context {
+			headers.push(("traceparent".to_string(), trace_context.get_traceparent()));
+			if let Some(ref trace_state) = trace_context.trace_state {
+				headers.push(("tracestate".to_string(), trace_state.clone()));
+			}
+		}
+		headers
+	}
+}
 
 /// Result for user functions.
 ///
@@ -153,6 +299,33 @@ pub struct RasServiceBuilder<T> {
 	runtime: tokio::runtime::Runtime,
 	service: Arc<T>,
 	socket_url: String,
+	request_limits: RequestLimits,
+	timeouts: Timeouts,
+	connection_limiter: Option<ConcurrencyLimiter>,
+	request_limiter: Option<ConcurrencyLimiter>,
+	get_request_limiters: HashMap<String, ConcurrencyLimiter>,
+	post_request_limiters: HashMap<String, ConcurrencyLimiter>,
+	rate_limiter: Option<RateLimiter>,
+	get_rate_limiters: HashMap<String, RateLimiter>,
+	post_rate_limiters: HashMap<String, RateLimiter>,
+	metrics_path: Option<String>,
+	health: ras_health::Health<T>,
+	health_paths: Option<(String, String)>,
+	graceful_shutdown: Option<ras_health::GracefulShutdown>,
+	span_exporter: Option<Arc<dyn ras_trace::SpanExporter>>,
+	cors: Option<ras_cors::CorsConfig>,
+	#[cfg(any(feature = "Gzip", feature = "Deflate", feature = "Brotli", feature = "Zstd"))]
+	compression_min_size: Option<usize>,
+	#[cfg(feature = "Authentication")]
+	authenticators: Vec<Arc<dyn Authenticator>>,
+	#[cfg(feature = "Authentication")]
+	get_auth_requirements: HashMap<String, AuthRequirement>,
+	#[cfg(feature = "Authentication")]
+	post_auth_requirements: HashMap<String, AuthRequirement>,
+	#[cfg(feature = "Authentication")]
+	token_life_time: u128,
+	#[cfg(feature = "Tls")]
+	tls_config: Option<Arc<ras_tls::TlsConfig>>,
 }
 
 impl<T: 'static> RasServiceBuilder<T>
@@ -181,7 +354,56 @@ where T: Sync + Send {
 			runtime: runtime,
 			service: Arc::new(service),
 			socket_url: "127.0.0.1:7777".to_string(),
+			request_limits: RequestLimits::default(),
+			timeouts: Timeouts::default(),
+			connection_limiter: None,
+			request_limiter: None,
+			get_request_limiters: HashMap::new(),
+			post_request_limiters: HashMap::new(),
+			rate_limiter: None,
+			get_rate_limiters: HashMap::new(),
+			post_rate_limiters: HashMap::new(),
+			metrics_path: None,
+			health: ras_health::Health::default(),
+			health_paths: None,
+			graceful_shutdown: None,
+			span_exporter: None,
+			cors: None,
+			#[cfg(any(feature = "Gzip", feature = "Deflate", feature = "Brotli", feature = "Zstd"))]
+			compression_min_size: None,
+			#[cfg(feature = "Authentication")]
+			authenticators: Vec::new(),
+			#[cfg(feature = "Authentication")]
+			get_auth_requirements: HashMap::new(),
+			#[cfg(feature = "Authentication")]
+			post_auth_requirements: HashMap::new(),
+			#[cfg(feature = "Authentication")]
+			token_life_time: ras_auth_client::DEFAULT_TOKEN_LIFE_TIME,
+			#[cfg(feature = "Tls")]
+			tls_config: None,
+		}
+	}
+
+	/// Create executor by configuration (runtime, address, limits, life time of token).
+	pub fn from_config(config: &ras_config::RasConfig, service: T)
+	-> RasServiceBuilder<T>
+	where T: Sync + Send {
+		let runtime = RasServiceBuilder::<T>::get_runtime(config.num_threads);
+		let mut builder = RasServiceBuilder::new(runtime, service)
+			.set_socket_url(&config.socket_url);
+		builder.request_limits = config.limits.clone();
+		builder.timeouts = config.timeouts.clone();
+		builder.connection_limiter = config.max_connections
+			.clone()
+			.map(ConcurrencyLimiter::new);
+		builder.request_limiter = config.max_requests
+			.clone()
+			.map(ConcurrencyLimiter::new);
+		#[cfg(feature = "Authentication")]
+		{
+			builder.token_life_time = u128::from(config.token_life_time);
 		}
+		builder
 	}
 
 	//interface:
@@ -194,6 +416,239 @@ where T: Sync + Send {
 		self
 	}
 
+	/// Specify max count of headers (100 by default), 431 if exceeded.
+	///
+	/// Backend on hyper accepts not more than 100 headers.
+	pub fn set_max_header_count(
+		mut self,
+		max_header_count: usize,
+	) -> Self {
+		self.request_limits.max_header_count = max_header_count;
+		self
+	}
+
+	/// Specify max size of request line and headers (16 KiB by default),
+	/// 431 if exceeded
+	pub fn set_max_header_bytes(
+		mut self,
+		max_header_bytes: usize,
+	) -> Self {
+		self.request_limits.max_header_bytes = max_header_bytes;
+		self
+	}
+
+	/// Specify max length of URI (8 KiB by default), 414 if exceeded
+	pub fn set_max_uri_length(
+		mut self,
+		max_uri_length: usize,
+	) -> Self {
+		self.request_limits.max_uri_length = max_uri_length;
+		self
+	}
+
+	/// Specify max size of body (1 MiB by default), 413 if exceeded
+	pub fn set_max_body_size(
+		mut self,
+		max_body_size: usize,
+	) -> Self {
+		self.request_limits.max_body_size = max_body_size;
+		self
+	}
+
+	/// Specify timeout of reading request line and headers (10 s by default),
+	/// 408 if exceeded (also timeout of TLS handshake)
+	pub fn set_read_header_timeout(
+		mut self,
+		timeout: Option<Duration>,
+	) -> Self {
+		self.timeouts.read_header = timeout;
+		self
+	}
+
+	/// Specify timeout of reading body (30 s by default), 408 if exceeded
+	pub fn set_read_body_timeout(
+		mut self,
+		timeout: Option<Duration>,
+	) -> Self {
+		self.timeouts.read_body = timeout;
+		self
+	}
+
+	/// Specify timeout of function (without timeout by default).
+	///
+	/// Task of RasResult::Async is aborted and 504 is returned.
+	pub fn set_handler_timeout(
+		mut self,
+		timeout: Option<Duration>,
+	) -> Self {
+		self.timeouts.handler = timeout;
+		self
+	}
+
+	/// Specify timeout of sending response (30 s by default)
+	pub fn set_write_timeout(
+		mut self,
+		timeout: Option<Duration>,
+	) -> Self {
+		self.timeouts.write = timeout;
+		self
+	}
+
+	/// Specify max count of concurrent connections.
+	///
+	/// Connections beyond limit and queue get 503 with Retry-After.
+	pub fn set_max_connections(
+		mut self,
+		limit: ConcurrencyLimit,
+	) -> Self {
+		self.connection_limiter = Some(ConcurrencyLimiter::new(limit));
+		self
+	}
+
+	/// Specify max count of requests in processing (for all functions).
+	///
+	/// Requests beyond limit and queue get 503 with Retry-After.
+	pub fn set_max_requests(
+		mut self,
+		limit: ConcurrencyLimit,
+	) -> Self {
+		self.request_limiter = Some(ConcurrencyLimiter::new(limit));
+		self
+	}
+
+	/// Specify max count of requests in processing for GET function
+	pub fn add_get_concurrency_limit(
+		mut self,
+		name: String,
+		limit: ConcurrencyLimit,
+	) -> Self {
+		self.get_request_limiters.insert(name, ConcurrencyLimiter::new(limit));
+		self
+	}
+
+	/// Specify max count of requests in processing for POST function
+	pub fn add_post_concurrency_limit(
+		mut self,
+		name: String,
+		limit: ConcurrencyLimit,
+	) -> Self {
+		self.post_request_limiters.insert(name, ConcurrencyLimiter::new(limit));
+		self
+	}
+
+	/// Specify rate limiter of requests (for all functions).
+	///
+	/// Requests beyond limit get 429 with RateLimit-* and Retry-After.
+	pub fn set_rate_limiter(
+		mut self,
+		rate_limiter: RateLimiter,
+	) -> Self {
+		self.rate_limiter = Some(rate_limiter);
+		self
+	}
+
+	/// Specify rate limiter of requests for GET function
+	pub fn add_get_rate_limiter(
+		mut self,
+		name: String,
+		rate_limiter: RateLimiter,
+	) -> Self {
+		self.get_rate_limiters.insert(name, rate_limiter);
+		self
+	}
+
+	/// Specify rate limiter of requests for POST function
+	pub fn add_post_rate_limiter(
+		mut self,
+		name: String,
+		rate_limiter: RateLimiter,
+	) -> Self {
+		self.post_rate_limiters.insert(name, rate_limiter);
+		self
+	}
+
+	/// Expose metrics (ras_metrics::registry) by GET path, for example "/metrics"
+	pub fn set_metrics_path(mut self, path: &str) -> Self {
+		self.metrics_path = Some(path.to_string());
+		self
+	}
+
+	/// Expose liveness and readiness probes by GET paths,
+	/// for example "/healthz" and "/readyz"
+	pub fn set_health_paths(mut self, liveness_path: &str, readiness_path: &str) -> Self {
+		self.health_paths = Some((liveness_path.to_string(), readiness_path.to_string()));
+		self
+	}
+
+	/// Add check of readiness (for example, ping of database of service).
+	///
+	/// Check returns error text, if service isn't ready.
+	pub fn add_readiness_check<F, R>(mut self, name: &str, check: F) -> Self
+	where
+		F: Fn(Arc<T>) -> R + Send + Sync + 'static,
+		R: std::future::Future<Output = Result<(), String>> + Send + 'static {
+		self.health.add_check(name, check);
+		self
+	}
+
+	/// Stop service by SIGTERM or Ctrl+C: readiness becomes false,
+	/// after delay accepting is stopped and opened connections are waited
+	pub fn set_graceful_shutdown(
+		mut self,
+		graceful_shutdown: ras_health::GracefulShutdown,
+	) -> Self {
+		self.graceful_shutdown = Some(graceful_shutdown);
+		self
+	}
+
+	/// Export server spans of functions (sampled by traceparent)
+	pub fn set_span_exporter(
+		mut self,
+		span_exporter: Arc<dyn ras_trace::SpanExporter>,
+	) -> Self {
+		self.span_exporter = Some(span_exporter);
+		self
+	}
+
+	/// Answer CORS preflight requests and add CORS headers to responses
+	pub fn set_cors(mut self, cors: ras_cors::CorsConfig) -> Self {
+		self.cors = Some(cors);
+		self
+	}
+
+	/// Compress responses not less than min_size bytes by Accept-Encoding
+	/// (compressed requests are decompressed always)
+	#[cfg(any(feature = "Gzip", feature = "Deflate", feature = "Brotli", feature = "Zstd"))]
+	pub fn set_compression(mut self, min_size: usize) -> Self {
+		self.compression_min_size = Some(min_size);
+		self
+	}
+
+	/// Specify TLS settings, listener accepts only TLS connections
+	#[cfg(feature = "Tls")]
+	pub fn set_tls_config(
+		mut self,
+		tls_config: ras_tls::TlsConfig,
+	) -> Self {
+		self.tls_config = Some(Arc::new(tls_config));
+		self
+	}
+
+	/// Specify PEM files of certificate chain and private key,
+	/// listener accepts only TLS connections.
+	///
+	/// Files are reloaded, when they are changed.
+	#[cfg(feature = "Tls")]
+	pub fn set_tls(
+		self,
+		cert_chain: &str,
+		private_key: &str,
+	) -> Self {
+		let tls_config = ras_tls::TlsConfig::new_from_files(cert_chain, private_key)
+			.expect("Panic! Can't load TLS certificate");
+		self.set_tls_config(tls_config)
+	}
+
 	/// Register GET function
 	pub fn add_get_function(
 		mut self,
@@ -216,36 +671,245 @@ where T: Sync + Send {
 		self
 	}
 
+	/// Add authenticator to auth layer
+	#[cfg(feature = "Authentication")]
+	pub fn add_authenticator(
+		mut self,
+		authenticator: Arc<dyn Authenticator>,
+	) -> Self {
+		self.authenticators.push(authenticator);
+		self
+	}
+
+	/// Add authenticator by bearer token, checked by RasAuthClient of service
+	#[cfg(feature = "Authentication")]
+	pub fn use_ras_auth_client<C>(self) -> Self
+	where
+		T: ras_auth_client::RasAuthClient<C>,
+		C: serde::de::DeserializeOwned + 'static {
+		let authenticator = ras_auth_layer::RasAuthClientAuthenticator::<T, C>::new(
+			self.service.clone()
+		);
+		self.add_authenticator(Arc::new(authenticator))
+	}
+
+	/// Specify life time of access token in ms (30 s by default).
+	///
+	/// It isn't applied automatically, pass get_token_life_time to
+	/// RasAuthClient::get_life_time_token, TokenSession, ServiceAuthClient and DenyList.
+	#[cfg(feature = "Authentication")]
+	pub fn set_token_life_time(
+		mut self,
+		token_life_time: u128,
+	) -> Self {
+		self.token_life_time = token_life_time;
+		self
+	}
+
+	/// Life time of access token in ms (from configuration)
+	#[cfg(feature = "Authentication")]
+	pub fn get_token_life_time(&self) -> u128 {
+		self.token_life_time
+	}
+
+	/// Specify requirement of auth layer for GET function
+	#[cfg(feature = "Authentication")]
+	pub fn add_get_auth_requirement(
+		mut self,
+		name: String,
+		requirement: AuthRequirement,
+	) -> Self {
+		self.get_auth_requirements.insert(name, requirement);
+		self
+	}
+
+	/// Specify requirement of auth layer for POST function
+	#[cfg(feature = "Authentication")]
+	pub fn add_post_auth_requirement(
+		mut self,
+		name: String,
+		requirement: AuthRequirement,
+	) -> Self {
+		self.post_auth_requirements.insert(name, requirement);
+		self
+	}
+
 	/// Start service.
 	///
-	/// Accepting connections loop is running in a blocking call.
+	/// Accepting connections loop is running in a blocking call
+	/// (until graceful shutdown, if it is specified).
 	pub fn run(self) {
 		let self_arc = Arc::new(self);
 		let for_start = self_arc.clone();
 		for_start.runtime.block_on(async move {
+			#[cfg(feature = "Tls")]
+			if let Some(ref tls_config) = self_arc.tls_config {
+				tls_config.spawn_reload(&tokio::runtime::Handle::current());
+			}
 			let listener = tokio::net::TcpListener::bind(&self_arc.socket_url)
 				.await
 				.expect("Panic! Can't bind to Tcp Sockert!");
-			loop {
-				let (mut stream, _addr) = match listener.accept().await {
-					Ok(val) => val,
-					Err(err) => {
-						eprintln!("Error! Can't accept connection: {:?}", err);
-						continue;
-					}
-				};
-				let ref_service = self_arc.clone();
-				tokio::spawn(async move {
-					let (http_status, result_data) = 
-						ref_service.connection_handler(&mut stream).await;
-					ref_service
-						.send_response(http_status, result_data, &mut stream).await;
-				});
+			let connections = Arc::new(());
+			let graceful_shutdown = match self_arc.graceful_shutdown.clone() {
+				Some(graceful_shutdown) => graceful_shutdown,
+				None => return self_arc.accept_loop(listener, connections).await,
+			};
+			let accept_loop = tokio::spawn(
+				self_arc.clone().accept_loop(listener, connections.clone())
+			);
+			ras_health::shutdown_signal().await;
+			self_arc.health.set_shutting_down();
+			tokio::time::sleep(graceful_shutdown.delay).await;
+			accept_loop.abort();
+			let deadline = tokio::time::Instant::now() + graceful_shutdown.timeout;
+			// every connection task holds clone of connections
+			while Arc::strong_count(&connections) > 1 && tokio::time::Instant::now() < deadline {
+				tokio::time::sleep(Duration::from_millis(100)).await;
 			}
 		});
 	}
 
 	//inner functions:
+	/// Accept connections and serve them in tasks
+	async fn accept_loop(self: Arc<Self>, listener: tokio::net::TcpListener, connections: Arc<()>) {
+		loop {
+			let (stream, addr) = match listener.accept().await {
+				Ok(val) => val,
+				Err(err) => {
+					eprintln!("Error! Can't accept connection: {:?}", err);
+					ras_metrics::service_metrics().accept_errors.inc(&[]);
+					continue;
+				}
+			};
+			let ref_service = self.clone();
+			let connection = connections.clone();
+			tokio::spawn(async move {
+				let _connection = connection;
+				let connection_permit = ras_concurrency::acquire(
+					ref_service.connection_limiter.as_ref()
+				).await;
+				let context = RequestContext {
+					peer_addr: Some(addr),
+					..RequestContext::default()
+				};
+				#[cfg(feature = "Tls")]
+				if let Some(ref tls_config) = ref_service.tls_config {
+					let handshake = tls_config.accept(stream, ref_service.timeouts.read_header);
+					let (tls_stream, peer_certificate) = match handshake.await {
+						Ok(val) => val,
+						Err(_) => return,
+					};
+					let context = RequestContext {
+						peer_certificate,
+						..context
+					};
+					let http2 = tls_stream.ssl().selected_alpn_protocol() == Some(b"h2");
+					ref_service.handle_stream(tls_stream, context, http2, connection_permit).await;
+					return;
+				}
+				// hyper detects h2c by itself
+				#[cfg(all(feature = "Http2", not(feature = "Hyper")))]
+				let http2 = {
+					let mut prefix = vec![0; ras_http2::PREFACE.len()];
+					match stream.peek(&mut prefix).await {
+						Ok(prefix_len) => prefix[..prefix_len].starts_with(b"PRI *"),
+						Err(err) => {
+							eprintln!("Error! Can't read data: {:?}", err);
+							return;
+						}
+					}
+				};
+				#[cfg(any(not(feature = "Http2"), feature = "Hyper"))]
+				let http2 = false;
+				ref_service.handle_stream(stream, context, http2, connection_permit).await;
+			});
+		}
+	}
+
+	/// Serve connection, or send rejection of connection limiter
+	async fn handle_stream<S>(
+		self: &Arc<Self>,
+		mut stream: S,
+		context: RequestContext,
+		http2: bool,
+		connection_permit: Result<Option<OwnedSemaphorePermit>, RasResponse>,
+	)
+	where S: AsyncRead + AsyncWrite + Unpin + Send + 'static {
+		let _connection_permit = match connection_permit {
+			Ok(connection_permit) => connection_permit,
+			Err(rejection) => {
+				eprintln!("Error! Too many connections");
+				// HTTP/2 connection is just closed
+				if !http2 {
+					self.send_response(rejection, &mut stream).await;
+				}
+				return;
+			}
+		};
+		self.serve_stream(stream, context, http2).await;
+	}
+
+	/// Serve connection by hyper
+	#[cfg(feature = "Hyper")]
+	async fn serve_stream<S>(
+		self: &Arc<Self>,
+		stream: S,
+		context: RequestContext,
+		http2: bool,
+	)
+	where S: AsyncRead + AsyncWrite + Unpin + Send + 'static {
+		ras_hyper::serve_connection(self.clone(), stream, context, http2).await;
+	}
+
+	#[cfg(not(feature = "Hyper"))]
+	#[cfg_attr(not(feature = "Http2"), allow(unused_variables))]
+	async fn serve_stream<S>(
+		self: &Arc<Self>,
+		stream: S,
+		context: RequestContext,
+		http2: bool,
+	)
+	where S: AsyncRead + AsyncWrite + Unpin {
+		#[cfg(feature = "Http2")]
+		if http2 {
+			self.http2_handler(stream, context).await;
+			return;
+		}
+		self.stream_handler(stream, context).await;
+	}
+
+	#[cfg_attr(feature = "Hyper", allow(dead_code))]
+	async fn stream_handler<S>(&self, mut stream: S, context: RequestContext)
+	where S: AsyncRead + AsyncWrite + Unpin {
+		let response = self.connection_handler(&mut stream, context).await;
+		self.send_response(response, &mut stream).await;
+	}
+
+	#[cfg(all(feature = "Http2", not(feature = "Hyper")))]
+	async fn http2_handler<S>(self: &Arc<Self>, stream: S, context: RequestContext)
+	where S: AsyncRead + AsyncWrite + Unpin {
+		let service = self.clone();
+		let handler = move |request: ras_http2::Http2Request| {
+			let service = service.clone();
+			let context = context.clone();
+			async move {
+				service.request_handler(
+					&request.method,
+					&request.path,
+					request.headers,
+					&request.body,
+					context,
+				).await
+			}
+		};
+		ras_http2::serve_connection(
+			stream,
+			self.request_limits.clone(),
+			self.timeouts.clone(),
+			handler,
+		).await;
+	}
+
 	fn post_functions(&self)
 	-> &HashMap<
 		String,
@@ -263,21 +927,42 @@ where T: Sync + Send {
 		func_name: &str,
 		input_data: Option<&str>,
 	) -> (HttpStatus, Option<String>) {
-		let result = match funcs.get(func_name) {
-			Some(func) => {
-				let runtime_handler = tokio::runtime::Handle::current();
-				func(runtime_handler, self.service.clone(), input_data)
-			},
-			None => RasResult::Sync(HttpStatus::NotFound, None),
-		};
-		match result {
-			RasResult::Sync(http_status, data) => (http_status, data),
-			RasResult::Async(join_handle) => {
-				join_handle
-					.await
-					.unwrap_or((HttpStatus::InternalServerError, None))
+		let span = ras_trace::ServerSpan::start(self.span_exporter.as_ref());
+		let result = async {
+			let result = match funcs.get(func_name) {
+				Some(func) => {
+					let runtime_handler = tokio::runtime::Handle::current();
+					func(runtime_handler, self.service.clone(), input_data)
+				},
+				None => RasResult::Sync(HttpStatus::NotFound, None),
+			};
+			match result {
+				RasResult::Sync(http_status, data) => (http_status, data),
+				RasResult::Async(mut join_handle) => {
+					let timeout = match self.timeouts.handler {
+						Some(timeout) => timeout,
+						None => return join_handle
+							.await
+							.unwrap_or((HttpStatus::InternalServerError, None)),
+					};
+					match tokio::time::timeout(timeout, &mut join_handle).await {
+						Ok(result) => result.unwrap_or((HttpStatus::InternalServerError, None)),
+						Err(_) => {
+							let request_id = RequestContext::current()
+								.map(|context| context.request_id.clone())
+								.unwrap_or_default();
+							eprintln!("Error! Timeout of function {} (request {})", func_name, request_id);
+							join_handle.abort();
+							(HttpStatus::GatewayTimeout, None)
+						}
+					}
+				}
 			}
+		}.await;
+		if let Some(span) = span {
+			span.finish(result.0.get_code());
 		}
+		result
 	}
 
 	fn get_functions(&self)
@@ -304,119 +989,541 @@ where T: Sync + Send {
 		&mut self.post_functions
 	}
 
-	async fn connection_handler(
+	async fn connection_handler<S>(
 		&self,
-		stream: &mut tokio::net::TcpStream,
-	) -> (HttpStatus, Option<String>) {
-		//TODO: take buffers sizes from config 
-		const BUFFER_SIZE: usize = 2048;
-		const HEADER_BUFFER_SIZE: usize = 32;
-
-		let mut buffer = [0; BUFFER_SIZE];
-		if let Err(err) = stream.readable().await {
-			eprintln!("Error! Can't read data: {:?}", err);
-				return (HttpStatus::InternalServerError, None);
-		}
-		let data_end = match stream.try_read(&mut buffer) {
-			Ok(0) => {
-				eprintln!("Error! Empty data");
-				return (HttpStatus::BadRequest, None);
-			},
-			Ok(n) => n,
-			Err(err) => {
-				eprintln!("Error! Can't read data: {:?}", err);
-				return (HttpStatus::BadRequest, None);
+		stream: &mut S,
+		context: RequestContext,
+	) -> RasResponse
+	where S: AsyncRead + Unpin {
+		const READ_BUFFER_SIZE: usize = 2048;
+
+		let limits = &self.request_limits;
+		let mut buffer = Vec::new();
+		let mut read_buffer = [0; READ_BUFFER_SIZE];
+		// read request line and headers
+		let header_deadline = self.timeouts.read_header
+			.map(|timeout| tokio::time::Instant::now() + timeout);
+		let data_start = loop {
+			let read_result = match read_before(stream, &mut read_buffer, header_deadline).await {
+				Some(read_result) => read_result,
+				None => return (HttpStatus::RequestTimeout, None).into(),
+			};
+			match read_result {
+				Ok(0) => {
+					eprintln!("Error! Empty data");
+					return (HttpStatus::BadRequest, None).into();
+				},
+				Ok(n) => buffer.extend_from_slice(&read_buffer[..n]),
+				Err(err) => {
+					eprintln!("Error! Can't read data: {:?}", err);
+					return (HttpStatus::BadRequest, None).into();
+				}
+			};
+			let mut headers = vec![httparse::EMPTY_HEADER; limits.max_header_count];
+			match httparse::Request::new(&mut headers).parse(&buffer) {
+				Ok(httparse::Status::Complete(data_offset)) => break data_offset,
+				Ok(httparse::Status::Partial) => {
+					let request_line_end = buffer
+						.windows(2)
+						.position(|window| window == b"\r\n");
+					if request_line_end.is_none() && buffer.len() > limits.max_uri_length {
+						return (HttpStatus::UriTooLong, None).into();
+					}
+					if buffer.len() > limits.max_header_bytes {
+						return (HttpStatus::RequestHeaderFieldsTooLarge, None).into();
+					}
+				},
+				Err(httparse::Error::TooManyHeaders) => {
+					return (HttpStatus::RequestHeaderFieldsTooLarge, None).into();
+				},
+				Err(_) => {
+					return (HttpStatus::BadRequest, None).into();
+				},
 			}
 		};
-		let mut headers = [httparse::EMPTY_HEADER; HEADER_BUFFER_SIZE];
+		let mut headers = vec![httparse::EMPTY_HEADER; limits.max_header_count];
 		let mut req = httparse::Request::new(&mut headers);
-		let data_start = match req.parse(&buffer) {
-			Ok(status) => {
-				match status {
-					httparse::Status::Complete(data_offset) => data_offset,
-					httparse::Status::Partial => {
-						return (HttpStatus::BadRequest, None)
-					},
-				}
-			},
-			Err(_) => {
-				return (HttpStatus::BadRequest, None)
-			},
-		};
+		if req.parse(&buffer).is_err() {
+			return (HttpStatus::BadRequest, None).into();
+		}
 		let path = match req.path {
 			Some(ref path) => path,
 			None => {
 				eprintln!("Error! Empty get-query path!");
-				return (HttpStatus::BadRequest, None);
+				return (HttpStatus::BadRequest, None).into();
+			}
+		};
+		if path.len() > limits.max_uri_length {
+			return (HttpStatus::UriTooLong, None).into();
+		}
+		if data_start > limits.max_header_bytes {
+			return (HttpStatus::RequestHeaderFieldsTooLarge, None).into();
+		}
+		let method = req.method.unwrap_or("");
+		let content_length = req.headers
+			.iter()
+			.find(|header| header.name.eq_ignore_ascii_case("Content-Length"))
+			.map(|header| std::str::from_utf8(header.value)
+				.ok()
+				.and_then(|value| value.trim().parse::<usize>().ok()));
+		let body_end = match content_length {
+			Some(Some(content_length)) if content_length > limits.max_body_size => {
+				return (HttpStatus::PayloadTooLarge, None).into();
+			},
+			Some(Some(content_length)) => data_start + content_length,
+			Some(None) => {
+				eprintln!("Error! Bad Content-Length");
+				return (HttpStatus::BadRequest, None).into();
+			},
+			None if buffer.len() - data_start > limits.max_body_size => {
+				return (HttpStatus::PayloadTooLarge, None).into();
+			},
+			None => buffer.len(),
+		};
+		// read rest of body
+		let mut body_buffer = buffer[data_start..].to_vec();
+		let body_deadline = self.timeouts.read_body
+			.map(|timeout| tokio::time::Instant::now() + timeout);
+		while method == "POST" && data_start + body_buffer.len() < body_end {
+			let read_result = match read_before(stream, &mut read_buffer, body_deadline).await {
+				Some(read_result) => read_result,
+				None => return (HttpStatus::RequestTimeout, None).into(),
+			};
+			match read_result {
+				Ok(0) => {
+					eprintln!("Error! Body is shorter than Content-Length");
+					return (HttpStatus::BadRequest, None).into();
+				},
+				Ok(n) => body_buffer.extend_from_slice(&read_buffer[..n]),
+				Err(err) => {
+					eprintln!("Error! Can't read data: {:?}", err);
+					return (HttpStatus::BadRequest, None).into();
+				}
+			};
+		}
+		let body = match method {
+			"POST" => &body_buffer[..body_end - data_start],
+			_ => &[],
+		};
+		let headers = req.headers
+			.iter()
+			.map(|header| (
+				header.name.to_string(),
+				String::from_utf8_lossy(header.value).to_string()
+			))
+			.collect();
+		self.request_handler(method, path, headers, body, context).await
+	}
+
+	/// Dispatch request to function (for HTTP/1.1 and HTTP/2), measure it.
+	///
+	/// Id of request is returned in header X-Request-Id.
+	async fn request_handler(
+		&self,
+		method: &str,
+		path: &str,
+		headers: Vec<(String, String)>,
+		body: &[u8],
+		context: RequestContext,
+	) -> RasResponse {
+		let request_id = ras_request_id::get_request_id(&headers);
+		let context = RequestContext {
+			request_id: request_id.clone(),
+			..context
+		};
+		if let Some(response) = self.cors
+			.as_ref()
+			.and_then(|cors| cors.preflight(method, &headers)) {
+			return response.add_header(ras_request_id::REQUEST_ID_HEADER, &request_id);
+		}
+		let origin = get_header(&headers, "Origin").map(|origin| origin.to_string());
+		#[cfg(any(feature = "Gzip", feature = "Deflate", feature = "Brotli", feature = "Zstd"))]
+		let accept_encoding = get_header(&headers, "Accept-Encoding").map(|value| value.to_string());
+		let response = async {
+			let path_only = path.split('?').next().unwrap_or("");
+			if method == "GET" && self.metrics_path.as_deref() == Some(path_only) {
+				return RasResponse::new(HttpStatus::OK, Some(ras_metrics::registry().render()))
+					.set_content_type(ras_metrics::CONTENT_TYPE);
 			}
+			if let (Some((liveness_path, readiness_path)), "GET") = (&self.health_paths, method) {
+				if liveness_path == path_only {
+					return self.health.liveness();
+				}
+				if readiness_path == path_only {
+					return self.health.readiness(self.service.clone()).await;
+				}
+			}
+			#[cfg(any(feature = "Gzip", feature = "Deflate", feature = "Brotli", feature = "Zstd"))]
+			let decompressed_body = match ras_compression::decompress_body(
+				get_header(&headers, "Content-Encoding"),
+				body,
+				self.request_limits.max_body_size,
+			) {
+				Ok(body) => body,
+				Err(http_status) => return RasResponse::new(http_status, None),
+			};
+			#[cfg(any(feature = "Gzip", feature = "Deflate", feature = "Brotli", feature = "Zstd"))]
+			let body = decompressed_body.as_ref();
+			let timer = ras_metrics::RequestTimer::start(method, self.get_route(method, path_only));
+			let response = self.route_request(method, path, headers, body, context).await;
+			timer.finish(response.http_status.get_code());
+			response
+		}.await;
+		#[cfg(any(feature = "Gzip", feature = "Deflate", feature = "Brotli", feature = "Zstd"))]
+		let response = match self.compression_min_size {
+			Some(min_size) => ras_compression::compress_response(
+				response,
+				accept_encoding.as_deref(),
+				min_size,
+			),
+			None => response,
 		};
+		let response = response.add_header(ras_request_id::REQUEST_ID_HEADER, &request_id);
+		match self.cors {
+			Some(ref cors) => cors.decorate(origin.as_deref(), response),
+			None => response,
+		}
+	}
+
+	/// Name of registered function for labels of metrics ("unknown" for others)
+	fn get_route(&self, method: &str, path: &str) -> &str {
+		let decode_path = urldecode::decode(path.to_string());
+		let func_name = decode_path.rsplit('/').next().unwrap_or("");
+		let functions = match method {
+			"GET" => self.get_functions(),
+			"POST" => self.post_functions(),
+			_ => return "unknown",
+		};
+		match functions.get_key_value(func_name) {
+			Some((name, _)) => name,
+			None => "unknown",
+		}
+	}
+
+	async fn route_request(
+		&self,
+		method: &str,
+		path: &str,
+		headers: Vec<(String, String)>,
+		body: &[u8],
+		context: RequestContext,
+	) -> RasResponse {
+		if path.len() > self.request_limits.max_uri_length {
+			return (HttpStatus::UriTooLong, None).into();
+		}
+		if headers.len() > self.request_limits.max_header_count {
+			return (HttpStatus::RequestHeaderFieldsTooLarge, None).into();
+		}
+		if body.len() > self.request_limits.max_body_size {
+			return (HttpStatus::PayloadTooLarge, None).into();
+		}
 		let decode_path = urldecode::decode(path.to_string());
 		let mut splited_path = match decode_path.split("/").last() {
 			Some(val) => val,
 			None => {
-				eprintln!("Error! Bad path");
-				return (HttpStatus::BadRequest, None);
+				eprintln!("Error! Bad path (request {})", context.request_id);
+				return (HttpStatus::BadRequest, None).into();
 			}
 		}.split("?");
 		let func_name = match splited_path.next() {
 			Some(val) => val,
 			None => {
-				eprintln!("Error! Bad path to api");
-				return (HttpStatus::BadRequest, None);
+				eprintln!("Error! Bad path to api (request {})", context.request_id);
+				return (HttpStatus::BadRequest, None).into();
 			}
 		};	
 		let params = splited_path.next();
-		let (func, input_data) = match req.method.unwrap_or("") {
+		let (func, input_data) = match method {
 			"GET" => {
 				(self.get_functions(), params)
 				// self.get_handler(func_name, params).await
 			},
 			"POST" => {
-				if BUFFER_SIZE < data_end 
-				|| data_start > data_end {
-					return (HttpStatus::BadRequest, None);
-				}
-				let content = match std::str::from_utf8(&buffer[data_start..data_end]) {
+				let content = match std::str::from_utf8(body) {
 					Ok(content) => content,
 					Err(err) => {
-						eprintln!("Error! Can't convert to UTF8: {:?}", err);
-						return (HttpStatus::BadRequest, None);
+						eprintln!(
+							"Error! Can't convert to UTF8: {:?} (request {})",
+							err,
+							context.request_id
+						);
+						return (HttpStatus::BadRequest, None).into();
 					},
 				};
 				(self.post_functions(), Some(content))
 			},
-			_ => return (HttpStatus::BadRequest, None),
+			_ => return (HttpStatus::BadRequest, None).into(),
+		};
+		#[allow(unused_mut)]
+		let mut context = RequestContext {
+			method: method.to_string(),
+			path: decode_path.clone(),
+			func_name: func_name.to_string(),
+			params: params.map(|params| params.to_string()),
+			trace_context: Some(ras_trace::TraceContext::from_request(&headers)),
+			headers,
+			..context
 		};
-		self.query_handle(func, func_name, input_data).await
+		// limiters by address are checked before authentication (for brute force of credentials)
+		let rate_limit = match self.check_rate_limits(method, func_name, &context, false, None) {
+			Ok(rate_limit) => rate_limit,
+			Err(rejection) => return rejection,
+		};
+		#[cfg(feature = "Authentication")]
+		{
+			let requirements = match method {
+				"GET" => &self.get_auth_requirements,
+				_ => &self.post_auth_requirements,
+			};
+			if let Err(rejection) = ras_auth_layer::authenticate(
+				&self.authenticators,
+				requirements.get(func_name),
+				&mut context,
+			) {
+				return rejection.into();
+			}
+		}
+		#[cfg(feature = "Authentication")]
+		let rate_limit = match self.check_rate_limits(method, func_name, &context, true, rate_limit) {
+			Ok(rate_limit) => rate_limit,
+			Err(rejection) => return rejection,
+		};
+		let _request_permit = match ras_concurrency::acquire(self.request_limiter.as_ref()).await {
+			Ok(request_permit) => request_permit,
+			Err(rejection) => return rejection,
+		};
+		let route_limiters = match method {
+			"GET" => &self.get_request_limiters,
+			_ => &self.post_request_limiters,
+		};
+		let _route_permit = match ras_concurrency::acquire(route_limiters.get(func_name)).await {
+			Ok(route_permit) => route_permit,
+			Err(rejection) => return rejection,
+		};
+		let mut response: RasResponse = REQUEST_CONTEXT.scope(
+			Arc::new(context),
+			self.query_handle(func, func_name, input_data)
+		).await.into();
+		if let Some(rate_limit) = rate_limit {
+			response.headers.extend(rate_limit.get_headers());
+		}
+		response
 	}
 
-	async fn send_response(
+	/// Check global and route rate limiters with keys by user (by_user)
+	/// or other keys, return decision with least remaining requests
+	/// (for headers, result is previous decision)
+	fn check_rate_limits(
 		&self,
-		status_line: HttpStatus,
-		content: Option<String>,
-		stream: &mut tokio::net::TcpStream
-	) {
-		let content = content.unwrap_or("".to_string());
-		let response = format!(
-			"{}\r\nContent-Length: {}\r\nContent-type: application/json; charset=utf-8\r\n\r\n{}",
-			status_line.get_string(),
-			content.len(),
-			content
-		);
-		match stream.write(response.as_bytes()).await {
-			Ok(_) => (),
-			Err(err) => {
-				eprintln!("Error! Can't send data: {:?}", err);
-				return;
-			}
+		method: &str,
+		func_name: &str,
+		context: &RequestContext,
+		by_user: bool,
+		mut result: Option<RateLimitDecision>,
+	) -> Result<Option<RateLimitDecision>, RasResponse> {
+		let route_limiters = match method {
+			"GET" => &self.get_rate_limiters,
+			_ => &self.post_rate_limiters,
 		};
-		match stream.flush().await {
-			Ok(_) => (),
-			Err(err) => {
-				eprintln!("Error! Can't send data: {:?}", err);
-				return;
+		let route_scope = format!("{} {}", method, func_name);
+		let limiters = [
+			self.rate_limiter.as_ref().map(|limiter| (limiter, "*")),
+			route_limiters.get(func_name).map(|limiter| (limiter, route_scope.as_str())),
+		];
+		let limiters = limiters
+			.into_iter()
+			.flatten()
+			.filter(|(limiter, _)| limiter.get_key().is_by_user() == by_user);
+		for (limiter, scope) in limiters {
+			let decision = limiter.check(scope, context)?;
+			if result.as_ref().is_none_or(|result| decision.remaining < result.remaining) {
+				result = Some(decision);
 			}
-		};		
+		}
+		Ok(result)
+	}
+
+	async fn send_response<S>(
+		&self,
+		mut response: RasResponse,
+		stream: &mut S
+	)
+	where S: AsyncWrite + Unpin {
+		let content = response.take_body();
+		let headers: String = response.headers
+			.iter()
+			.map(|(name, value)| format!("{}: {}\r\n", name, value))
+			.collect();
+		let mut response = format!(
+			"{}\r\nContent-Length: {}\r\nContent-type: {}\r\n{}\r\n",
+			response.http_status.get_string(),
+			content.len(),
+			response.content_type,
+			headers
+		).into_bytes();
+		response.extend_from_slice(&content);
+		let send = async {
+			stream.write_all(&response).await?;
+			stream.flush().await
+		};
+		let result = match self.timeouts.write {
+			Some(timeout) => match tokio::time::timeout(timeout, send).await {
+				Ok(result) => result,
+				Err(_) => {
+					eprintln!("Error! Timeout of sending data");
+					return;
+				}
+			},
+			None => send.await,
+		};
+		if let Err(err) = result {
+			eprintln!("Error! Can't send data: {:?}", err);
+		}
+	}
+}
+
+/// Limits of request
+#[derive(serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone)]
+#[serde(default)]
+pub struct RequestLimits {
+	pub max_header_count: usize,
+	/// Size of request line and headers
+	pub max_header_bytes: usize,
+	pub max_uri_length: usize,
+	pub max_body_size: usize,
+}
+
+impl Default for RequestLimits {
+	fn default() -> Self {
+		RequestLimits {
+			max_header_count: 100,
+			max_header_bytes: 16 * 1024,
+			max_uri_length: 8 * 1024,
+			max_body_size: 1024 * 1024,
+		}
+	}
+}
+
+/// Timeouts of connection and function (None - without timeout).
+///
+/// In configuration timeouts are in milliseconds.
+#[derive(serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone)]
+#[serde(default)]
+pub struct Timeouts {
+	/// Reading of request line and headers, 408 if exceeded (also TLS handshake)
+	#[serde(with = "duration_ms")]
+	pub read_header: Option<Duration>,
+	/// Reading of body, 408 if exceeded
+	#[serde(with = "duration_ms")]
+	pub read_body: Option<Duration>,
+	/// Execution of function (RasResult::Async), task is aborted and 504 is returned
+	#[serde(with = "duration_ms")]
+	pub handler: Option<Duration>,
+	/// Sending of response, connection is closed if exceeded
+	#[serde(with = "duration_ms")]
+	pub write: Option<Duration>,
+}
+
+impl Default for Timeouts {
+	fn default() -> Self {
+		Timeouts {
+			read_header: Some(Duration::from_secs(10)),
+			read_body: Some(Duration::from_secs(30)),
+			handler: None,
+			write: Some(Duration::from_secs(30)),
+		}
+	}
+}
+
+mod duration_ms {
+	use super::Duration;
+	use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+	pub fn serialize<S: Serializer>(
+		duration: &Option<Duration>,
+		serializer: S,
+	) -> Result<S::Ok, S::Error> {
+		duration
+			.map(|duration| duration.as_millis() as u64)
+			.serialize(serializer)
+	}
+
+	pub fn deserialize<'de, D: Deserializer<'de>>(
+		deserializer: D,
+	) -> Result<Option<Duration>, D::Error> {
+		Ok(Option::<u64>::deserialize(deserializer)?.map(Duration::from_millis))
+	}
+}
+
+/// Value of header (name is case insensitive)
+fn get_header<'a>(headers: &'a [(String, String)], name: &str) -> Option<&'a str> {
+	headers
+		.iter()
+		.find(|(header_name, _)| header_name.eq_ignore_ascii_case(name))
+		.map(|(_, value)| value.as_str())
+}
+
+/// Read from stream, None if deadline is passed
+async fn read_before<S>(
+	stream: &mut S,
+	buffer: &mut [u8],
+	deadline: Option<tokio::time::Instant>,
+) -> Option<std::io::Result<usize>>
+where S: AsyncRead + Unpin {
+	match deadline {
+		Some(deadline) => tokio::time::timeout_at(deadline, stream.read(buffer)).await.ok(),
+		None => Some(stream.read(buffer).await),
+	}
+}
+
+/// Response: status, body and additional headers
+#[derive(Debug)]
+pub struct RasResponse {
+	pub http_status: HttpStatus,
+	pub content: Option<String>,
+	pub content_type: String,
+	/// Compressed content, is sent instead of content
+	pub encoded_content: Option<Vec<u8>>,
+	pub headers: Vec<(String, String)>,
+}
+
+impl RasResponse {
+	pub fn new(http_status: HttpStatus, content: Option<String>) -> RasResponse {
+		RasResponse {
+			http_status,
+			content,
+			content_type: "application/json; charset=utf-8".to_string(),
+			encoded_content: None,
+			headers: Vec::new(),
+		}
+	}
+
+	/// Specify Content-Type (json by default)
+	pub fn set_content_type(mut self, content_type: &str) -> Self {
+		self.content_type = content_type.to_string();
+		self
+	}
+
+	/// Body for sending (compressed content, if exists)
+	pub fn take_body(&mut self) -> Vec<u8> {
+		match self.encoded_content.take() {
+			Some(encoded_content) => encoded_content,
+			None => self.content.take().unwrap_or_default().into_bytes(),
+		}
+	}
+
+	/// Add header to response
+	pub fn add_header(mut self, name: &str, value: &str) -> Self {
+		self.headers.push((name.to_string(), value.to_string()));
+		self
+	}
+}
+
+impl From<(HttpStatus, Option<String>)> for RasResponse {
+	fn from((http_status, content): (HttpStatus, Option<String>)) -> Self {
+		RasResponse::new(http_status, content)
 	}
 }
 
@@ -431,10 +1538,40 @@ pub enum HttpStatus {
 	AuthenticationTimeout,
 	InternalServerError,
 	NotFound,
+	PayloadTooLarge,
+	UriTooLong,
+	RequestHeaderFieldsTooLarge,
+	RequestTimeout,
+	GatewayTimeout,
+	ServiceUnavailable,
+	TooManyRequests,
+	UnsupportedMediaType,
 }
 
 /// Get header line
 impl HttpStatus {
+	/// Numeric code of status
+	pub fn get_code(&self) -> u16 {
+		match self {
+			HttpStatus::OK => 200,
+			HttpStatus::BadRequest => 400,
+			HttpStatus::Forbidden => 403,
+			HttpStatus::Unauthorized => 401,
+			HttpStatus::AuthenticationTimeout => 419,
+			HttpStatus::NotFound => 404,
+			HttpStatus::InternalServerError => 500,
+			HttpStatus::PayloadTooLarge => 413,
+			HttpStatus::UriTooLong => 414,
+			HttpStatus::RequestHeaderFieldsTooLarge => 431,
+			HttpStatus::RequestTimeout => 408,
+			HttpStatus::GatewayTimeout => 504,
+			HttpStatus::ServiceUnavailable => 503,
+			HttpStatus::TooManyRequests => 429,
+			HttpStatus::UnsupportedMediaType => 415,
+		}
+	}
+
+
 	pub fn get_string(&self) -> String {
 		match self {
 			HttpStatus::OK => String::from("HTTP/1.1 200 OK"),
@@ -446,6 +1583,16 @@ impl HttpStatus {
 			HttpStatus::NotFound => String::from("HTTP/1.1 404 Not Found"),
 			HttpStatus::InternalServerError => 
 				String::from("HTTP/1.1 500 InternalServerError"),
+			HttpStatus::PayloadTooLarge => String::from("HTTP/1.1 413 Payload Too Large"),
+			HttpStatus::UriTooLong => String::from("HTTP/1.1 414 URI Too Long"),
+			HttpStatus::RequestHeaderFieldsTooLarge =>
+				String::from("HTTP/1.1 431 Request Header Fields Too Large"),
+			HttpStatus::RequestTimeout => String::from("HTTP/1.1 408 Request Timeout"),
+			HttpStatus::GatewayTimeout => String::from("HTTP/1.1 504 Gateway Timeout"),
+			HttpStatus::ServiceUnavailable => String::from("HTTP/1.1 503 Service Unavailable"),
+			HttpStatus::TooManyRequests => String::from("HTTP/1.1 429 Too Many Requests"),
+			HttpStatus::UnsupportedMediaType =>
+				String::from("HTTP/1.1 415 Unsupported Media Type"),
 		}
 	}
 }
@@ -485,4 +1632,197 @@ struct SomeService {
 			assert_eq!(data, None);
 		});
 	}
-}
\ No newline at end of file
+
+	fn some_test_post(
+		_runtime: tokio::runtime::Handle,
+		_self_service: Arc<SomeService>,
+		params: Option<&str>)
+	-> RasResult {
+		RasResult::Sync(
+			HttpStatus::OK,
+			params.map(|params| params.to_string())
+		)
+	}
+
+	#[test]
+	fn request_limits() {
+		let runtime = RasServiceBuilder::<SomeService>::get_runtime(1);
+		let rsb = RasServiceBuilder::new(runtime, SomeService {})
+			.add_post_function("some_test_post".to_string(), some_test_post)
+			.set_max_header_count(4)
+			.set_max_uri_length(64)
+			.set_max_body_size(16);
+		let arc_rsb = Arc::new(rsb);
+		let arc_rsb_2 = arc_rsb.clone();
+		arc_rsb_2.runtime.block_on(async move {
+			let handle = |chunks: Vec<String>| {
+				let arc_rsb = arc_rsb.clone();
+				async move {
+					let (mut client, mut server) = tokio::io::duplex(64 * 1024);
+					for chunk in chunks {
+						client.write_all(chunk.as_bytes()).await.unwrap();
+						tokio::task::yield_now().await;
+					}
+					arc_rsb.connection_handler(&mut server, RequestContext::default()).await
+				}
+			};
+			let headers = "X-Trace: 1\r\n".repeat(5);
+			assert_eq!(
+				handle(vec![format!("POST /api/some_test_post HTTP/1.1\r\n{}\r\n", headers)])
+					.await.http_status,
+				HttpStatus::RequestHeaderFieldsTooLarge
+			);
+			assert_eq!(
+				handle(vec![format!("POST /api/{} HTTP/1.1\r\n\r\n", "a".repeat(64))])
+					.await.http_status,
+				HttpStatus::UriTooLong
+			);
+			assert_eq!(
+				handle(vec!["POST /api/some_test_post HTTP/1.1\r\nContent-Length: 17\r\n\r\n".to_string()])
+					.await.http_status,
+				HttpStatus::PayloadTooLarge
+			);
+			let response = handle(vec![
+				"POST /api/some_test_post HTTP/1.1\r\n".to_string(),
+				"Content-Length: 11\r\n\r\n{\"id\":".to_string(),
+				"\"42\"}".to_string(),
+			]).await;
+			assert_eq!(response.http_status, HttpStatus::OK);
+			assert_eq!(response.content.unwrap(), r#"{"id":"42"}"#);
+		});
+	}
+
+	fn some_test_slow_get(
+		runtime: tokio::runtime::Handle,
+		_self_service: Arc<SomeService>,
+		_params: Option<&str>)
+	-> RasResult {
+		RasResult::Async(runtime.spawn(async {
+			tokio::time::sleep(Duration::from_secs(10)).await;
+			(HttpStatus::OK, None)
+		}))
+	}
+
+	#[test]
+	fn metrics_endpoint() {
+		let runtime = RasServiceBuilder::<SomeService>::get_runtime(1);
+		let builder = RasServiceBuilder::new(runtime, SomeService {})
+			.add_get_function("some_test_get".to_string(), some_test_get)
+			.set_metrics_path("/metrics");
+		builder.runtime.block_on(async {
+			let response = builder.request_handler(
+				"GET",
+				"/api/some_test_get?5",
+				vec![("X-Request-Id".to_string(), "req-1".to_string())],
+				&[],
+				RequestContext::default(),
+			).await;
+			assert_eq!(response.http_status, HttpStatus::OK);
+			assert_eq!(response.headers, vec![("X-Request-Id".to_string(), "req-1".to_string())]);
+			builder.request_handler(
+				"GET", "/api/missing", Vec::new(), &[], RequestContext::default(),
+			).await;
+			builder.request_handler(
+				"PROPFIND", "/api/some_test_get", Vec::new(), &[], RequestContext::default(),
+			).await;
+			let response = builder.request_handler(
+				"GET", "/metrics", Vec::new(), &[], RequestContext::default(),
+			).await;
+			assert_eq!(response.content_type, ras_metrics::CONTENT_TYPE);
+			let metrics = response.content.unwrap();
+			assert!(metrics.contains(
+				r#"ras_requests_total{method="GET",route="some_test_get",status="200"}"#
+			));
+			assert!(metrics.contains(
+				r#"ras_request_errors_total{method="GET",route="unknown",status="404"}"#
+			));
+			assert!(metrics.contains(r#"method="OTHER",route="unknown""#));
+			assert!(!metrics.contains("PROPFIND"));
+			assert!(metrics.contains("# TYPE ras_request_duration_seconds histogram"));
+		});
+	}
+
+	#[test]
+	fn server_span_is_exported() {
+		let exporter = Arc::new(ras_trace::InMemoryExporter::new());
+		let runtime = RasServiceBuilder::<SomeService>::get_runtime(1);
+		let builder = RasServiceBuilder::new(runtime, SomeService {})
+			.add_get_function("some_test_get".to_string(), some_test_get)
+			.set_span_exporter(exporter.clone());
+		builder.runtime.block_on(async {
+			let traceparent = "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01";
+			builder.request_handler(
+				"GET",
+				"/api/some_test_get",
+				vec![("traceparent".to_string(), traceparent.to_string())],
+				&[],
+				RequestContext::default(),
+			).await;
+			builder.request_handler(
+				"GET",
+				"/api/some_test_get",
+				vec![("traceparent".to_string(), traceparent.replace("-01", "-00"))],
+				&[],
+				RequestContext::default(),
+			).await;
+		});
+		let spans = exporter.get_spans();
+		assert_eq!(spans.len(), 1);
+		assert_eq!(spans[0].name, "GET some_test_get");
+		assert_eq!(spans[0].context.get_trace_id(), "4bf92f3577b34da6a3ce929d0e0e4736");
+	}
+
+	#[test]
+	fn timeouts() {
+		let runtime = RasServiceBuilder::<SomeService>::get_runtime(1);
+		let rsb = RasServiceBuilder::new(runtime, SomeService {})
+			.add_get_function("some_test_slow_get".to_string(), some_test_slow_get)
+			.set_read_header_timeout(Some(Duration::from_millis(50)))
+			.set_handler_timeout(Some(Duration::from_millis(50)));
+		let arc_rsb = Arc::new(rsb);
+		let arc_rsb_2 = arc_rsb.clone();
+		arc_rsb_2.runtime.block_on(async move {
+			let (http_status, _) =
+				arc_rsb.query_handle(arc_rsb.get_functions(), "some_test_slow_get", None).await;
+			assert_eq!(http_status, HttpStatus::GatewayTimeout);
+			let (mut client, mut server) = tokio::io::duplex(1024);
+			client.write_all(b"GET /api/some_test_slow_get HTTP/1.1\r\n").await.unwrap();
+			let response = arc_rsb.connection_handler(&mut server, RequestContext::default()).await;
+			assert_eq!(response.http_status, HttpStatus::RequestTimeout);
+		});
+	}
+
+	#[cfg(feature = "Authentication")]
+	struct RejectingAuthenticator;
+
+	#[cfg(feature = "Authentication")]
+	impl Authenticator for RejectingAuthenticator {
+		fn authenticate(&self, _request: &RequestContext) -> ras_auth_layer::AuthResult {
+			ras_auth_layer::AuthResult::Rejected(HttpStatus::Unauthorized)
+		}
+	}
+
+	#[cfg(feature = "Authentication")]
+	#[test]
+	fn rate_limit_before_authentication() {
+		use ras_rate_limit::{RateLimit, RateLimitKey};
+		let runtime = RasServiceBuilder::<SomeService>::get_runtime(1);
+		let builder = RasServiceBuilder::new(runtime, SomeService {})
+			.add_get_function("some_test_get".to_string(), some_test_get)
+			.add_authenticator(Arc::new(RejectingAuthenticator))
+			.add_get_auth_requirement("some_test_get".to_string(), AuthRequirement::authenticated())
+			.set_rate_limiter(RateLimiter::new(RateLimit::per_minute(2), RateLimitKey::PeerAddr));
+		builder.runtime.block_on(async {
+			for expected_status in [
+				HttpStatus::Unauthorized,
+				HttpStatus::Unauthorized,
+				HttpStatus::TooManyRequests,
+			] {
+				let response = builder.request_handler(
+					"GET", "/api/some_test_get", Vec::new(), &[], RequestContext::default(),
+				).await;
+				assert_eq!(response.http_status, expected_status);
+			}
+		});
+	}
+}
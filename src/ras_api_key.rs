@@ -0,0 +1,224 @@
+use crate::{
+	Arc,
+	HashMap,
+	HttpStatus,
+	RequestContext,
+	ras_auth_layer::{Authenticator, AuthResult, Principal},
+	ras_helper::parse_get_params,
+};
+use serde::{Serialize, Deserialize};
+use std::sync::RwLock;
+
+/// Hash of API key (hex of sha256), keys are stored only by hash.
+pub fn hash_api_key(key: &str) -> String {
+	openssl::sha::sha256(key.as_bytes())
+		.iter()
+		.map(|byte| format!("{:02x}", byte))
+		.collect()
+}
+
+/// Store of API keys.
+///
+/// Implement it for keep keys in your storage (for example, database).
+pub trait ApiKeyStore: Sync + Send {
+	/// Find principal by hash of key
+	fn find_by_hash(&self, key_hash: &str) -> Option<Principal>;
+}
+
+/// Entry of API key store
+#[derive(Serialize, Deserialize)]
+#[derive(Debug, Clone)]
+pub struct ApiKeyEntry {
+	/// See hash_api_key
+	pub key_hash: String,
+	#[serde(flatten)]
+	pub principal: Principal,
+}
+
+/// In-memory store of API keys
+#[derive(Default)]
+pub struct MemoryApiKeyStore {
+	keys: RwLock<HashMap<String, Principal>>,
+}
+
+impl MemoryApiKeyStore {
+	pub fn new() -> MemoryApiKeyStore {
+		MemoryApiKeyStore::default()
+	}
+
+	/// Add key (only hash of key is stored)
+	pub fn add_key(&self, key: &str, principal: Principal) {
+		self.add_key_hash(&hash_api_key(key), principal);
+	}
+
+	pub fn add_key_hash(&self, key_hash: &str, principal: Principal) {
+		self.keys
+			.write()
+			.unwrap_or_else(|err| err.into_inner())
+			.insert(key_hash.to_string(), principal);
+	}
+
+	pub fn remove_key_hash(&self, key_hash: &str) {
+		self.keys
+			.write()
+			.unwrap_or_else(|err| err.into_inner())
+			.remove(key_hash);
+	}
+
+	/// Replace all keys
+	pub fn set_entries(&self, entries: Vec<ApiKeyEntry>) {
+		*self.keys.write().unwrap_or_else(|err| err.into_inner()) = entries
+			.into_iter()
+			.map(|entry| (entry.key_hash, entry.principal))
+			.collect();
+	}
+}
+
+impl ApiKeyStore for MemoryApiKeyStore {
+	fn find_by_hash(&self, key_hash: &str) -> Option<Principal> {
+		self.keys
+			.read()
+			.unwrap_or_else(|err| err.into_inner())
+			.get(key_hash)
+			.cloned()
+	}
+}
+
+/// Store of API keys in json file.
+///
+/// Format of file:
+///
+/// [{"key_hash": "sha256 hex", "user_name": "cron", "user_role": 1, "scopes": []}]
+pub struct FileApiKeyStore {
+	path: String,
+	keys: MemoryApiKeyStore,
+}
+
+impl FileApiKeyStore {
+	/// Load keys from file
+	pub fn new_from_file(path: &str) -> Result<FileApiKeyStore, ()> {
+		let store = FileApiKeyStore {
+			path: path.to_string(),
+			keys: MemoryApiKeyStore::new(),
+		};
+		store.reload()?;
+		Ok(store)
+	}
+
+	/// Load keys from file again
+	pub fn reload(&self) -> Result<(), ()> {
+		let json = match std::fs::read_to_string(&self.path) {
+			Ok(json) => json,
+			Err(err) => {
+				eprintln!("Error! Can't read API keys file {}: {:?}", self.path, err);
+				return Err(());
+			}
+		};
+		match serde_json::from_str(&json) {
+			Ok(entries) => {
+				self.keys.set_entries(entries);
+				Ok(())
+			},
+			Err(err) => {
+				eprintln!("Error! Bad format of API keys file {}: {:?}", self.path, err);
+				Err(())
+			}
+		}
+	}
+}
+
+impl ApiKeyStore for FileApiKeyStore {
+	fn find_by_hash(&self, key_hash: &str) -> Option<Principal> {
+		self.keys.find_by_hash(key_hash)
+	}
+}
+
+/// Authenticator by API key.
+///
+/// Key is taken from header (X-Api-Key by default)
+/// or GET-parameter (api_key by default).
+pub struct ApiKeyAuthenticator {
+	store: Arc<dyn ApiKeyStore>,
+	header_name: String,
+	query_param: Option<String>,
+}
+
+impl ApiKeyAuthenticator {
+	pub fn new(store: Arc<dyn ApiKeyStore>) -> ApiKeyAuthenticator {
+		ApiKeyAuthenticator {
+			store,
+			header_name: "X-Api-Key".to_string(),
+			query_param: Some("api_key".to_string()),
+		}
+	}
+
+	/// Specify header with key
+	pub fn set_header_name(mut self, header_name: &str) -> Self {
+		self.header_name = header_name.to_string();
+		self
+	}
+
+	/// Specify GET-parameter with key (None - key is accepted only by header)
+	pub fn set_query_param(mut self, query_param: Option<&str>) -> Self {
+		self.query_param = query_param.map(|query_param| query_param.to_string());
+		self
+	}
+
+	fn get_key(&self, request: &RequestContext) -> Option<String> {
+		if let Some(key) = request.header(&self.header_name) {
+			return Some(key.trim().to_string());
+		}
+		let query_param = self.query_param.as_ref()?;
+		parse_get_params(request.params.as_ref()?)
+			.remove(query_param)
+			.flatten()
+	}
+}
+
+impl Authenticator for ApiKeyAuthenticator {
+	fn authenticate(&self, request: &RequestContext) -> AuthResult {
+		let key = match self.get_key(request) {
+			Some(key) => key,
+			None => return AuthResult::NoCredentials,
+		};
+		match self.store.find_by_hash(&hash_api_key(&key)) {
+			Some(principal) => AuthResult::Authenticated(principal),
+			None => {
+				eprintln!("Error! Unknown API key");
+				AuthResult::Rejected(HttpStatus::Unauthorized)
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn key_by_header_and_query_param() {
+		let store = MemoryApiKeyStore::new();
+		let principal = Principal {
+			user_name: "cron".to_string(),
+			user_role: 0b0000_0001,
+			scopes: Vec::new(),
+		};
+		store.add_key("secret", principal.clone());
+		let authenticator = ApiKeyAuthenticator::new(Arc::new(store));
+		let request = RequestContext {
+			headers: vec![("x-api-key".to_string(), "secret".to_string())],
+			..RequestContext::default()
+		};
+		assert!(matches!(
+			authenticator.authenticate(&request),
+			AuthResult::Authenticated(ref found) if *found == principal
+		));
+		let request = RequestContext {
+			params: Some("api_key=wrong".to_string()),
+			..RequestContext::default()
+		};
+		assert!(matches!(authenticator.authenticate(&request), AuthResult::Rejected(_)));
+		let request = RequestContext::default();
+		assert!(matches!(authenticator.authenticate(&request), AuthResult::NoCredentials));
+	}
+}
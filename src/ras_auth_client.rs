@@ -1,10 +1,19 @@
 use crate::{
 	Verifier,
-	ErrorStack
+	ErrorStack,
+	PKey,
+	Public,
+	ras_key_store::KeyStore,
+	ras_deny_list::DenyList,
+	ras_metrics,
 };
-use serde::{Serialize, Deserialize};
+use serde::{Serialize, Deserialize, de::DeserializeOwned};
 use serde_json::Value;
-use reqwest::Client;
+use reqwest::{Client, RequestBuilder, Response};
+use std::time::Instant;
+
+/// Default life time of access token in ms
+pub const DEFAULT_TOKEN_LIFE_TIME: u128 = 30_000;
 
 pub trait Token {
 	fn get_b64(&self) -> Result<String, ()> where Self: Serialize {
@@ -29,16 +38,66 @@ pub trait Token {
 /// **** **00 - 7 some roles
 ///
 /// For example: Function for Administator and first role must have rule 0000 0110
+///
+/// Token id is optional, it is used for revocation of token.
+///
+/// Extra claims (tenant id, scopes, session id, custom attributes)
+/// are contained into claims, they are flattened into json of token.
+///
+/// # Examples
+///
+/// ```ignore
+/// #[derive(Serialize, Deserialize)]
+/// struct Claims {
+///     tenant_id: String,
+/// }
+///
+/// impl RasAuthClient<Claims> for Service { ... }
+///
+/// let token: AccessToken<Claims> = service.check_and_get_access_token(token_str)?;
+/// println!("{}", token.claims.tenant_id);
+/// ```
 #[derive(Serialize, Deserialize)]
 #[derive(Debug)]
-pub struct AccessToken {
+pub struct AccessToken<C = ()> {
 	pub user_name: String,
 	pub user_role: u8,
 	pub date_spawn: u128,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub token_id: Option<String>,
+	#[serde(flatten)]
+	pub claims: C,
 }
 
+/// Role bit of service
+pub const ROLE_SERVICE: u8 = 0b0000_0001;
+/// Role bit of administrator
+pub const ROLE_ADMINISTRATOR: u8 = 0b0000_0010;
+
 impl AccessToken {
-	pub fn new_from_str(b64_json: &str) -> Result<AccessToken, ()> {
+	/// Create token, spawned now
+	pub fn new(user_name: String, user_role: u8) -> AccessToken {
+		AccessToken::new_with_claims(user_name, user_role, ())
+	}
+}
+
+impl<C> AccessToken<C> {
+	/// Create token with extra claims, spawned now
+	pub fn new_with_claims(user_name: String, user_role: u8, claims: C) -> AccessToken<C> {
+		AccessToken {
+			user_name,
+			user_role,
+			date_spawn: std::time::SystemTime::now()
+				.duration_since(std::time::UNIX_EPOCH)
+				.unwrap_or(std::time::Duration::ZERO)
+				.as_millis(),
+			token_id: None,
+			claims,
+		}
+	}
+
+	pub fn new_from_str(b64_json: &str) -> Result<AccessToken<C>, ()>
+	where C: DeserializeOwned {
 		match serde_json::from_str(
 			std::str::from_utf8(
 				&base64::decode(b64_json).unwrap()
@@ -65,35 +124,138 @@ impl AccessToken {
 	}
 }
 
-impl Token for AccessToken {}
+impl<C: Serialize> Token for AccessToken<C> {}
 
-pub trait RasAuthClient {
+/// Client side of ras_auth.
+///
+/// C is type of extra claims in AccessToken (without extra claims by default).
+pub trait RasAuthClient<C: DeserializeOwned = ()> {
 	/// Must return Verifier from public key
 	/// For example:
 	/// fn get_verifier(&self) -> std::result::Result<Verifier, ErrorStack> {
 	///		Verifier::new(MessageDigest::sha256(), &self.public_key_for_token)
 	/// }
+	///
+	/// Not needed, if get_key_store is implemented.
 	fn get_verifier(&self) 
-	-> std::result::Result<Verifier, ErrorStack>;
+	-> std::result::Result<Verifier, ErrorStack> {
+		eprintln!("Error! get_verifier or get_key_store of RasAuthClient is not implemented");
+		Err(ErrorStack::get())
+	}
+
+	/// Must return KeyStore, if keys for token are rotated.
+	/// For example:
+	/// fn get_key_store(&self) -> Option<&KeyStore> {
+	///         Some(&self.key_store)
+	/// }
+	fn get_key_store(&self) -> Option<&KeyStore> {
+		None
+	}
+
+	/// Must return DenyList, if revoked tokens are checked by built-in deny list.
+	/// For example:
+	/// fn get_deny_list(&self) -> Option<&DenyList> {
+	///         Some(&self.deny_list)
+	/// }
+	fn get_deny_list(&self) -> Option<&DenyList> {
+		None
+	}
+
+	/// Hook for revocation: return true, if token is revoked.
+	///
+	/// By default token is checked by deny list (if exist).
+	fn is_token_revoked(&self, token: &AccessToken<C>) -> bool {
+		match self.get_deny_list() {
+			Some(deny_list) => deny_list.is_denied(token),
+			None => false,
+		}
+	}
+
+	/// Hook for revocation of JWT: return true, if JWT is revoked.
+	///
+	/// By default claims jti, sub and iat are checked by deny list (if exist).
+	fn is_jwt_revoked(&self, claims: &Value) -> bool {
+		match self.get_deny_list() {
+			Some(deny_list) => deny_list.is_jwt_denied(claims),
+			None => false,
+		}
+	}
 	
+	/// Scopes (OAuth2) of token, they are checked by auth layer.
+	///
+	/// By default token hasn't scopes, override it for take scopes from claims.
+	fn get_scopes(&self, _token: &AccessToken<C>) -> Vec<String> {
+		Vec::new()
+	}
+
+	/// Life time of token in ms (DEFAULT_TOKEN_LIFE_TIME by default)
 	fn get_life_time_token(&self) -> u128 {
-		30_000_u128
+		DEFAULT_TOKEN_LIFE_TIME
 	}
 
 	/// Check signature and life time token and return AccessToken from str token.
+	///
+	/// Token format: json@@sign or json@@sign@@key_id
 	fn check_and_get_access_token(&self, token_str: &str)
-	-> Result<AccessToken, ()> {
+	-> Result<AccessToken<C>, ()> {
 		let splited_token: Vec<&str> = token_str.split("@@").collect();
 		if splited_token.len() < 2 
-		|| !self.check_token_sign(splited_token[0], splited_token[1]) {
+		|| !self.check_token_sign_by_key(
+			splited_token[0],
+			splited_token[1],
+			splited_token.get(2).copied()
+		) {
+			ras_metrics::auth_failure("bad_signature");
 			return Err(());
 		}
-		let token = AccessToken::new_from_str(splited_token[0])?;
+		let token = AccessToken::new_from_str(splited_token[0]).map_err(|_| {
+			ras_metrics::auth_failure("bad_token");
+		})?;
 		let life_time_token = self.get_life_time_token();
-		if token.check_time(&life_time_token) {
-			Ok(token)
-		} else {
-			Err(())
+		if !token.check_time(&life_time_token) {
+			ras_metrics::auth_failure("expired");
+			return Err(());
+		}
+		if self.is_token_revoked(&token) {
+			eprintln!("Error! Token of user {} is revoked", token.user_name);
+			ras_metrics::auth_failure("revoked");
+			return Err(());
+		}
+		Ok(token)
+	}
+
+	/// Check signature, life time and revocation of JWT and return its claims.
+	///
+	/// Keys for JWT are taken from key store
+	/// (for example, KeyStore::from_jwks_url for OIDC issuer).
+	fn check_and_get_jwt_claims(&self, token_str: &str) -> Result<Value, ()> {
+		let claims = match self.get_key_store() {
+			Some(key_store) => key_store.verify_jwt(token_str).map_err(|_| {
+				ras_metrics::auth_failure("bad_jwt");
+			})?,
+			None => {
+				eprintln!("Error! Key store is needed for check JWT");
+				return Err(());
+			}
+		};
+		if self.is_jwt_revoked(&claims) {
+			eprintln!("Error! JWT of user {} is revoked", claims["sub"]);
+			ras_metrics::auth_failure("revoked");
+			return Err(());
+		}
+		Ok(claims)
+	}
+
+	/// Check signature by key store (if exist) or by get_verifier.
+	fn check_token_sign_by_key(
+		&self,
+		json: &str,
+		sign: &str,
+		key_id: Option<&str>
+	) -> bool {
+		match self.get_key_store() {
+			Some(key_store) => key_store.verify(json, sign, key_id),
+			None => self.check_token_sign(json, sign),
 		}
 	}
 
@@ -184,3 +346,483 @@ pub async fn get_public_key_for_token(
 		panic!("Panic! Can't get key for token: {:?}", err);
 	})
 }
+
+
+/// Login to ras_auth and return response with tokens.
+pub async fn login_to_ras_auth(
+	client: &Client,
+	login: &str,
+	password: &str,
+	ras_auth_uri: &str
+) -> Result<Value, ()> {
+	let query = serde_json::json!({
+		"name": login,
+		"password": password,
+	});
+	post_to_ras_auth(client, ras_auth_uri, "login", query).await
+}
+
+/// Get new tokens from ras_auth by refresh token.
+///
+/// Request: {"refresh_token": "..."} to /refresh,
+/// response contains access_token and (optional) new refresh_token.
+pub async fn refresh_in_ras_auth(
+	client: &Client,
+	refresh_token: &str,
+	ras_auth_uri: &str
+) -> Result<Value, ()> {
+	let query = serde_json::json!({
+		"refresh_token": refresh_token,
+	});
+	post_to_ras_auth(client, ras_auth_uri, "refresh", query).await
+}
+
+async fn post_to_ras_auth(
+	client: &Client,
+	ras_auth_uri: &str,
+	func_name: &str,
+	query: Value
+) -> Result<Value, ()> {
+	let response = match client.post(format!("{}/{}", ras_auth_uri, func_name))
+		.body(query.to_string())
+		.send().await {
+		Ok(response) => response,
+		Err(err) => {
+			eprintln!("Error! Can't call {} of ras_auth: {:?}", func_name, err);
+			return Err(());
+		}
+	};
+	if !response.status().is_success() {
+		eprintln!("Error! Can't call {} of ras_auth: {}", func_name, response.status());
+		return Err(());
+	}
+	let response_json = match response.text().await {
+		Ok(response_json) => response_json,
+		Err(err) => {
+			eprintln!("Error! Can't read {} response from ras_auth: {:?}", func_name, err);
+			return Err(());
+		}
+	};
+	match serde_json::from_str(&response_json) {
+		Ok(tokens) => Ok(tokens),
+		Err(err) => {
+			eprintln!("Error! Bad {} response from ras_auth: {:?}", func_name, err);
+			Err(())
+		}
+	}
+}
+
+/// Get public keys for token from ras_auth.
+///
+/// Response of ras_auth may contain one key:
+///
+/// {"public_key": "base64 pem", "key_id": "optional id"}
+///
+/// or list of keys:
+///
+/// {"public_keys": [{"public_key": "base64 pem", "key_id": "id"}]}
+///
+/// Key without id is returned with None.
+pub async fn get_public_keys_from_ras_auth(
+	client: &Client,
+	access_token: &str,
+	ras_auth_uri: &str
+) -> Result<Vec<(Option<String>, PKey<Public>)>, ()> {
+	let query = serde_json::json!({
+		"token": access_token,
+	}).to_string();
+	let response = match client.post(format!("{}/get_public_key", ras_auth_uri))
+		.body(query)
+		.send().await {
+		Ok(response) => response,
+		Err(err) => {
+			eprintln!("Error! Can't get key for token: {:?}", err);
+			return Err(());
+		}
+	};
+	if !response.status().is_success() {
+		eprintln!("Error! Can't get key for token: {}", response.status());
+		return Err(());
+	}
+	let response_json = match response.text().await {
+		Ok(response_json) => response_json,
+		Err(err) => {
+			eprintln!("Error! Can't read public key response from ras_auth: {:?}", err);
+			return Err(());
+		}
+	};
+	let response_json: Value = match serde_json::from_str(&response_json) {
+		Ok(response_json) => response_json,
+		Err(err) => {
+			eprintln!("Error! Bad public key response from ras_auth: {:?}", err);
+			return Err(());
+		}
+	};
+	let entries = match response_json["public_keys"].as_array() {
+		Some(entries) => entries.iter().collect(),
+		None => vec![&response_json],
+	};
+	let mut keys = Vec::new();
+	for entry in entries {
+		let pem = match entry["public_key"].as_str().map(base64::decode) {
+			Some(Ok(pem)) => pem,
+			_ => {
+				eprintln!("Error! Don't exist public key in response");
+				return Err(());
+			}
+		};
+		let key = match PKey::public_key_from_pem(&pem) {
+			Ok(key) => key,
+			Err(err) => {
+				eprintln!("Error! Bad public key in response: {:?}", err);
+				return Err(());
+			}
+		};
+		keys.push((entry["key_id"].as_str().map(|id| id.to_string()), key));
+	}
+	Ok(keys)
+}
+
+struct SessionTokens {
+	access_token: String,
+	refresh_token: Option<String>,
+	obtained_at: Instant,
+}
+
+/// Session with ras_auth on client side.
+///
+/// Holds access token and refresh token,
+/// access token is refreshed before end of its life time.
+/// If refresh token is missing or rejected, session logs in again by credentials (if specified).
+///
+/// # Examples
+///
+/// ```ignore
+/// let session = TokenSession::new(ras_auth_uri)
+///     .set_credentials(login, password);
+/// session.login().await?;
+/// let response = session.send(session.http_client().get(uri)).await?;
+/// ```
+pub struct TokenSession {
+	ras_auth_uri: String,
+	client: Client,
+	credentials: Option<(String, String)>,
+	token_life_time: u128,
+	renew_before: u128,
+	tokens: tokio::sync::Mutex<Option<SessionTokens>>,
+}
+
+impl TokenSession {
+	//constructor:
+	/// Create session without tokens,
+	/// use set_credentials and login, or set_tokens.
+	pub fn new(ras_auth_uri: String) -> TokenSession {
+		TokenSession {
+			ras_auth_uri,
+			client: Client::new(),
+			credentials: None,
+			token_life_time: DEFAULT_TOKEN_LIFE_TIME,
+			renew_before: 5_000_u128,
+			tokens: tokio::sync::Mutex::new(None),
+		}
+	}
+
+	/// Specify credentials for login
+	pub fn set_credentials(mut self, login: String, password: String) -> Self {
+		self.credentials = Some((login, password));
+		self
+	}
+
+	/// Specify life time of access token in ms (as get_life_time_token of RasAuthClient).
+	pub fn set_token_life_time(mut self, token_life_time: u128) -> Self {
+		self.token_life_time = token_life_time;
+		self
+	}
+
+	/// Specify, how long before end of life time access token is refreshed (in ms).
+	pub fn set_renew_before(mut self, renew_before: u128) -> Self {
+		self.renew_before = renew_before;
+		self
+	}
+
+	/// Specify http client for calls
+	pub fn set_http_client(mut self, client: Client) -> Self {
+		self.client = client;
+		self
+	}
+
+	//interface:
+	/// Http client for build requests
+	pub fn http_client(&self) -> &Client {
+		&self.client
+	}
+
+	/// Use tokens, received in other way (for example, login response of user).
+	pub async fn set_tokens(&self, access_token: String, refresh_token: Option<String>) {
+		*self.tokens.lock().await = Some(SessionTokens {
+			access_token,
+			refresh_token,
+			obtained_at: Instant::now(),
+		});
+	}
+
+	/// Login to ras_auth by credentials
+	pub async fn login(&self) -> Result<(), ()> {
+		let mut tokens = self.tokens.lock().await;
+		*tokens = Some(self.login_by_credentials().await?);
+		Ok(())
+	}
+
+	/// Refresh access token now
+	pub async fn refresh(&self) -> Result<(), ()> {
+		let mut tokens = self.tokens.lock().await;
+		self.renew(&mut tokens).await
+	}
+
+	/// Return access token, refresh it before end of its life time.
+	pub async fn get_access_token(&self) -> Result<String, ()> {
+		let mut tokens = self.tokens.lock().await;
+		let is_fresh = match *tokens {
+			Some(ref tokens) => tokens.obtained_at.elapsed().as_millis() + self.renew_before
+				< self.token_life_time,
+			None => false,
+		};
+		if !is_fresh {
+			self.renew(&mut tokens).await?;
+		}
+		match *tokens {
+			Some(ref tokens) => Ok(tokens.access_token.clone()),
+			None => Err(()),
+		}
+	}
+
+	/// Attach access token to request (header Authorization: Bearer).
+	pub async fn authorize(&self, request: RequestBuilder) -> Result<RequestBuilder, ()> {
+		let access_token = self.get_access_token().await?;
+		Ok(request.bearer_auth(access_token))
+	}
+
+	/// Send request with access token.
+	///
+	/// If server answers 419 Authentication Timeout,
+	/// token is refreshed and request is retried once.
+	pub async fn send(&self, request: RequestBuilder) -> Result<Response, ()> {
+		self.send_with_retry(request, &[419]).await
+	}
+
+	/// Send request with access token and retry once,
+	/// if server answers one of statuses (after refresh of token).
+	pub async fn send_with_retry(
+		&self,
+		request: RequestBuilder,
+		retry_statuses: &[u16],
+	) -> Result<Response, ()> {
+		let retry_request = request.try_clone();
+		let response = self.send_authorized(request).await?;
+		match retry_request {
+			Some(retry_request) if retry_statuses.contains(&response.status().as_u16()) => {
+				self.refresh().await?;
+				self.send_authorized(retry_request).await
+			},
+			_ => Ok(response),
+		}
+	}
+
+	//inner functions:
+	async fn renew(&self, tokens: &mut Option<SessionTokens>) -> Result<(), ()> {
+		let refresh_token = tokens.as_ref().and_then(|tokens| tokens.refresh_token.clone());
+		if let Some(refresh_token) = refresh_token {
+			let obtained_at = Instant::now();
+			let refreshed = refresh_in_ras_auth(&self.client, &refresh_token, &self.ras_auth_uri)
+				.await
+				.and_then(|response| parse_session_tokens(&response, obtained_at));
+			match refreshed {
+				Ok(mut refreshed) => {
+					if refreshed.refresh_token.is_none() {
+						refreshed.refresh_token = Some(refresh_token);
+					}
+					*tokens = Some(refreshed);
+					return Ok(());
+				},
+				Err(_) => eprintln!("Error! Can't refresh access token"),
+			}
+		}
+		*tokens = Some(self.login_by_credentials().await?);
+		Ok(())
+	}
+
+	async fn login_by_credentials(&self) -> Result<SessionTokens, ()> {
+		let (login, password) = match self.credentials {
+			Some((ref login, ref password)) => (login, password),
+			None => {
+				eprintln!("Error! Credentials for login aren't specified");
+				return Err(());
+			}
+		};
+		let obtained_at = Instant::now();
+		let response = login_to_ras_auth(&self.client, login, password, &self.ras_auth_uri).await?;
+		parse_session_tokens(&response, obtained_at)
+	}
+
+	async fn send_authorized(&self, request: RequestBuilder) -> Result<Response, ()> {
+		match self.authorize(request).await?.send().await {
+			Ok(response) => Ok(response),
+			Err(err) => {
+				eprintln!("Error! Can't send request: {:?}", err);
+				Err(())
+			}
+		}
+	}
+}
+
+fn parse_session_tokens(response: &Value, obtained_at: Instant) -> Result<SessionTokens, ()> {
+	match response["access_token"].as_str() {
+		Some(access_token) => Ok(SessionTokens {
+			access_token: access_token.to_string(),
+			refresh_token: response["refresh_token"].as_str().map(|token| token.to_string()),
+			obtained_at,
+		}),
+		None => {
+			eprintln!("Error! Can't find access token in response");
+			Err(())
+		}
+	}
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+	use super::*;
+	use std::sync::{Arc, Mutex};
+	use tokio::{
+		io::{AsyncReadExt, AsyncWriteExt},
+		net::{TcpListener, TcpStream},
+	};
+
+	/// Stub of ras_auth and of protected service.
+	///
+	/// /login returns access-N and refresh-N, /refresh returns refreshed-N
+	/// (refresh token "bad" is rejected), /api answers 419 for access token
+	/// from login and 200 for refreshed token.
+	pub(crate) struct StubAuthServer {
+		pub uri: String,
+		calls: Arc<Mutex<Vec<String>>>,
+	}
+
+	impl StubAuthServer {
+		pub(crate) async fn start() -> StubAuthServer {
+			let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+			let uri = format!("http://{}", listener.local_addr().unwrap());
+			let calls = Arc::new(Mutex::new(Vec::new()));
+			let server_calls = calls.clone();
+			tokio::spawn(async move {
+				while let Ok((stream, _)) = listener.accept().await {
+					tokio::spawn(handle(stream, server_calls.clone()));
+				}
+			});
+			StubAuthServer {uri, calls}
+		}
+
+		/// Paths of received requests
+		pub(crate) fn calls(&self) -> Vec<String> {
+			self.calls.lock().unwrap().clone()
+		}
+	}
+
+	async fn handle(mut stream: TcpStream, calls: Arc<Mutex<Vec<String>>>) {
+		let mut request = Vec::new();
+		let mut buffer = [0_u8; 4096];
+		let head_end = loop {
+			let count = stream.read(&mut buffer).await.unwrap();
+			if count == 0 {
+				return;
+			}
+			request.extend_from_slice(&buffer[..count]);
+			if let Some(position) = request.windows(4).position(|window| window == b"\r\n\r\n") {
+				break position + 4;
+			}
+		};
+		let head = String::from_utf8_lossy(&request[..head_end]).to_string();
+		let content_length = head
+			.lines()
+			.filter_map(|line| line.split_once(':'))
+			.find(|(name, _)| name.eq_ignore_ascii_case("Content-Length"))
+			.map_or(0, |(_, value)| value.trim().parse().unwrap());
+		while request.len() < head_end + content_length {
+			let count = stream.read(&mut buffer).await.unwrap();
+			request.extend_from_slice(&buffer[..count]);
+		}
+		let body = String::from_utf8_lossy(&request[head_end..]).to_string();
+		let path = head.split_whitespace().nth(1).unwrap_or("").to_string();
+		let count = {
+			let mut calls = calls.lock().unwrap();
+			calls.push(path.clone());
+			calls.iter().filter(|call| **call == path).count()
+		};
+		let (status, content) = match path.as_str() {
+			"/login" => ("200 OK", serde_json::json!({
+				"access_token": format!("access-{}", count),
+				"refresh_token": format!("refresh-{}", count),
+			}).to_string()),
+			"/refresh" if body.contains("\"bad\"") => ("401 Unauthorized", String::new()),
+			"/refresh" => ("200 OK", serde_json::json!({
+				"access_token": format!("refreshed-{}", count),
+			}).to_string()),
+			"/api" if head.contains("Bearer refreshed-") => ("200 OK", String::new()),
+			"/api" => ("419 Authentication Timeout", String::new()),
+			_ => ("404 Not Found", String::new()),
+		};
+		let response = format!(
+			"HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+			status,
+			content.len(),
+			content
+		);
+		let _ = stream.write_all(response.as_bytes()).await;
+	}
+
+	fn runtime() -> tokio::runtime::Runtime {
+		tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap()
+	}
+
+	#[test]
+	fn session_refreshes_tokens() {
+		runtime().block_on(async {
+			let server = StubAuthServer::start().await;
+			let session = TokenSession::new(server.uri.clone())
+				.set_credentials("service".to_string(), "password".to_string());
+			session.set_tokens("access-0".to_string(), Some("refresh-0".to_string())).await;
+			session.refresh().await.unwrap();
+			assert_eq!(session.get_access_token().await.unwrap(), "refreshed-1");
+			// refresh token is kept, if ras_auth doesn't return new one
+			session.refresh().await.unwrap();
+			assert_eq!(session.get_access_token().await.unwrap(), "refreshed-2");
+			assert_eq!(server.calls(), ["/refresh", "/refresh"]);
+
+			// rejected refresh token leads to login by credentials
+			session.set_tokens("access-0".to_string(), Some("bad".to_string())).await;
+			session.refresh().await.unwrap();
+			assert_eq!(session.get_access_token().await.unwrap(), "access-1");
+			assert_eq!(server.calls(), ["/refresh", "/refresh", "/refresh", "/login"]);
+		});
+	}
+
+	#[test]
+	fn session_retries_after_419() {
+		runtime().block_on(async {
+			let server = StubAuthServer::start().await;
+			let session = TokenSession::new(server.uri.clone())
+				.set_credentials("service".to_string(), "password".to_string());
+			session.login().await.unwrap();
+			let request = session.http_client().get(format!("{}/api", server.uri));
+			let response = session.send(request).await.unwrap();
+			assert_eq!(response.status().as_u16(), 200);
+			assert_eq!(server.calls(), ["/login", "/api", "/refresh", "/api"]);
+
+			// other statuses aren't retried
+			let request = session.http_client().get(format!("{}/missing", server.uri));
+			let response = session.send(request).await.unwrap();
+			assert_eq!(response.status().as_u16(), 404);
+			assert_eq!(server.calls().len(), 5);
+		});
+	}
+}
@@ -0,0 +1,278 @@
+use crate::{
+	Arc,
+	HttpStatus,
+	RequestContext,
+	ras_auth_client::RasAuthClient,
+};
+use serde::{Serialize, Deserialize, de::DeserializeOwned};
+use serde_json::Value;
+use std::marker::PhantomData;
+
+/// Authenticated caller, which handlers see via RequestContext.
+///
+/// User role is a bitmask, as user_role of AccessToken.
+#[derive(Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Principal {
+	pub user_name: String,
+	pub user_role: u8,
+	/// OAuth2 scopes (for example, "orders:read")
+	#[serde(default)]
+	pub scopes: Vec<String>,
+}
+
+impl Principal {
+	pub fn has_scope(&self, scope: &str) -> bool {
+		self.scopes.iter().any(|own_scope| own_scope == scope)
+	}
+}
+
+/// Result of authenticator
+pub enum AuthResult {
+	Authenticated(Principal),
+	/// Credentials are present, but invalid (for example, 401 or 419)
+	Rejected(HttpStatus),
+	/// Credentials for this authenticator are missing
+	NoCredentials,
+}
+
+/// Authenticator of auth layer.
+///
+/// Authenticators are checked in order of adding,
+/// first authenticator, which finds credentials, makes decision.
+pub trait Authenticator: Sync + Send {
+	fn authenticate(&self, request: &RequestContext) -> AuthResult;
+}
+
+/// Requirement of route.
+///
+/// # Examples
+///
+/// ```ignore
+/// RasServiceBuilder::new(runtime, service)
+///     .use_ras_auth_client::<()>()
+///     .add_post_function("orders".to_string(), create_order)
+///     .add_post_auth_requirement(
+///         "orders".to_string(),
+///         AuthRequirement::scopes(&["orders:write"])
+///     )
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct AuthRequirement {
+	/// At least one of role bits is needed (0 - any role)
+	pub role_mask: u8,
+	/// All scopes are needed
+	pub scopes: Vec<String>,
+}
+
+impl AuthRequirement {
+	/// Any authenticated caller
+	pub fn authenticated() -> AuthRequirement {
+		AuthRequirement::default()
+	}
+
+	/// Caller with at least one of role bits
+	pub fn roles(role_mask: u8) -> AuthRequirement {
+		AuthRequirement {
+			role_mask,
+			scopes: Vec::new(),
+		}
+	}
+
+	/// Caller with all scopes
+	pub fn scopes(scopes: &[&str]) -> AuthRequirement {
+		AuthRequirement {
+			role_mask: 0,
+			scopes: scopes.iter().map(|scope| scope.to_string()).collect(),
+		}
+	}
+
+	/// Add scopes to requirement
+	pub fn and_scopes(mut self, scopes: &[&str]) -> Self {
+		self.scopes.extend(scopes.iter().map(|scope| scope.to_string()));
+		self
+	}
+
+	/// Check principal, return status and body for rejection.
+	pub fn check(&self, principal: &Principal) -> Result<(), (HttpStatus, Option<String>)> {
+		if self.role_mask != 0 && principal.user_role & self.role_mask == 0 {
+			return Err((HttpStatus::Forbidden, Some(serde_json::json!({
+				"error": "insufficient_role",
+			}).to_string())));
+		}
+		let missing_scopes: Vec<&String> = self.scopes
+			.iter()
+			.filter(|scope| !principal.has_scope(scope))
+			.collect();
+		if !missing_scopes.is_empty() {
+			return Err((HttpStatus::Forbidden, Some(serde_json::json!({
+				"error": "insufficient_scope",
+				"missing_scopes": missing_scopes,
+			}).to_string())));
+		}
+		Ok(())
+	}
+}
+
+/// Authenticator by header "Authorization: Bearer token",
+/// token is checked by RasAuthClient of service.
+///
+/// Tokens of ras_auth (json@@sign) and JWT are supported.
+/// Scopes of ras_auth token are taken from get_scopes,
+/// scopes of JWT are taken from claim "scope" (or "scp").
+pub struct RasAuthClientAuthenticator<T, C> {
+	service: Arc<T>,
+	claims: PhantomData<fn() -> C>,
+}
+
+impl<T, C> RasAuthClientAuthenticator<T, C>
+where T: RasAuthClient<C> + Sync + Send, C: DeserializeOwned {
+	pub fn new(service: Arc<T>) -> RasAuthClientAuthenticator<T, C> {
+		RasAuthClientAuthenticator {
+			service,
+			claims: PhantomData,
+		}
+	}
+}
+
+impl<T, C> Authenticator for RasAuthClientAuthenticator<T, C>
+where T: RasAuthClient<C> + Sync + Send, C: DeserializeOwned {
+	fn authenticate(&self, request: &RequestContext) -> AuthResult {
+		let token_str = match get_authorization(request, "Bearer") {
+			Some(token_str) => token_str,
+			None => return AuthResult::NoCredentials,
+		};
+		if token_str.contains("@@") {
+			return match self.service.check_and_get_access_token(token_str) {
+				Ok(token) => AuthResult::Authenticated(Principal {
+					scopes: self.service.get_scopes(&token),
+					user_name: token.user_name,
+					user_role: token.user_role,
+				}),
+				Err(_) => AuthResult::Rejected(HttpStatus::Unauthorized),
+			};
+		}
+		match self.service.check_and_get_jwt_claims(token_str).and_then(|claims| {
+			get_principal_from_claims(&claims)
+		}) {
+			Ok(principal) => AuthResult::Authenticated(principal),
+			Err(_) => AuthResult::Rejected(HttpStatus::Unauthorized),
+		}
+	}
+}
+
+/// Principal from claims of JWT: sub, user_role, scope (or scp).
+///
+/// Token with user_role, which isn't u8, is rejected.
+fn get_principal_from_claims(claims: &Value) -> Result<Principal, ()> {
+	let user_role = match claims["user_role"] {
+		Value::Null => 0,
+		ref user_role => match user_role.as_u64().map(u8::try_from) {
+			Some(Ok(user_role)) => user_role,
+			_ => {
+				eprintln!("Error! Bad user_role in JWT: {}", user_role);
+				return Err(());
+			}
+		},
+	};
+	let scopes = match claims["scope"].as_str() {
+		Some(scope) => scope.split_whitespace().map(|scope| scope.to_string()).collect(),
+		None => claims["scp"]
+			.as_array()
+			.map(|scopes| scopes
+				.iter()
+				.filter_map(|scope| scope.as_str().map(|scope| scope.to_string()))
+				.collect())
+			.unwrap_or_default(),
+	};
+	Ok(Principal {
+		user_name: claims["sub"].as_str().unwrap_or("").to_string(),
+		user_role,
+		scopes,
+	})
+}
+
+/// Value of header Authorization with scheme (case insensitive).
+pub fn get_authorization<'a>(request: &'a RequestContext, scheme: &str) -> Option<&'a str> {
+	let value = request.header("Authorization")?.trim();
+	let (value_scheme, credentials) = value.split_once(' ')?;
+	if value_scheme.eq_ignore_ascii_case(scheme) {
+		Some(credentials.trim())
+	} else {
+		None
+	}
+}
+
+/// Authenticate request and check requirement of route.
+///
+/// Principal is saved into context.
+/// If route hasn't requirement, rejection of credentials is ignored.
+pub fn authenticate(
+	authenticators: &[Arc<dyn Authenticator>],
+	requirement: Option<&AuthRequirement>,
+	context: &mut RequestContext,
+) -> Result<(), (HttpStatus, Option<String>)> {
+	let mut rejection = None;
+	for authenticator in authenticators {
+		match authenticator.authenticate(context) {
+			AuthResult::Authenticated(principal) => {
+				context.principal = Some(principal);
+				break;
+			},
+			AuthResult::Rejected(http_status) => {
+				rejection = Some(http_status);
+				break;
+			},
+			AuthResult::NoCredentials => continue,
+		}
+	}
+	let requirement = match requirement {
+		Some(requirement) => requirement,
+		None => return Ok(()),
+	};
+	match context.principal {
+		Some(ref principal) => requirement.check(principal),
+		None => Err((rejection.unwrap_or(HttpStatus::Unauthorized), None)),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn missing_scopes_are_listed() {
+		let principal = Principal {
+			user_name: "user".to_string(),
+			user_role: 0,
+			scopes: vec!["orders:read".to_string()],
+		};
+		let requirement = AuthRequirement::scopes(&["orders:read"]);
+		assert!(requirement.check(&principal).is_ok());
+		let requirement = AuthRequirement::scopes(&["orders:read", "orders:write"]);
+		let (http_status, body) = requirement.check(&principal).unwrap_err();
+		assert_eq!(http_status, HttpStatus::Forbidden);
+		assert_eq!(
+			body.unwrap(),
+			r#"{"error":"insufficient_scope","missing_scopes":["orders:write"]}"#
+		);
+		assert!(AuthRequirement::roles(0b0000_0110).check(&principal).is_err());
+	}
+
+	#[test]
+	fn principal_from_jwt_claims() {
+		let principal = get_principal_from_claims(&serde_json::json!({
+			"sub": "user",
+			"user_role": 2,
+			"scope": "orders:read orders:write",
+		})).unwrap();
+		assert_eq!(principal.user_name, "user");
+		assert_eq!(principal.user_role, 2);
+		assert!(principal.has_scope("orders:write"));
+		let principal = get_principal_from_claims(&serde_json::json!({"sub": "user"})).unwrap();
+		assert_eq!(principal.user_role, 0);
+		assert!(get_principal_from_claims(&serde_json::json!({"user_role": 257})).is_err());
+		assert!(get_principal_from_claims(&serde_json::json!({"user_role": -1})).is_err());
+		assert!(get_principal_from_claims(&serde_json::json!({"user_role": "2"})).is_err());
+	}
+}
@@ -0,0 +1,266 @@
+use crate::{
+	Arc,
+	HashMap,
+	HttpStatus,
+	RequestContext,
+	ras_auth_layer::{Authenticator, AuthResult, Principal, get_authorization},
+};
+use std::sync::{OnceLock, RwLock};
+
+/// Provider of credentials for Basic authentication.
+///
+/// Password hash must be in PHC format of argon2 ($argon2id$...)
+/// with feature "Argon2", or in format of bcrypt ($2b$...) with feature "Bcrypt".
+pub trait CredentialsProvider: Sync + Send {
+	/// Return hash of password and principal of user
+	fn get_credentials(&self, user_name: &str) -> Option<(String, Principal)>;
+}
+
+/// In-memory provider of credentials
+#[derive(Default)]
+pub struct MemoryCredentialsProvider {
+	users: RwLock<HashMap<String, (String, Principal)>>,
+}
+
+impl MemoryCredentialsProvider {
+	pub fn new() -> MemoryCredentialsProvider {
+		MemoryCredentialsProvider::default()
+	}
+
+	/// Add user with hash of password
+	pub fn add_user(&self, password_hash: &str, principal: Principal) {
+		self.users
+			.write()
+			.unwrap_or_else(|err| err.into_inner())
+			.insert(principal.user_name.clone(), (password_hash.to_string(), principal));
+	}
+
+	pub fn remove_user(&self, user_name: &str) {
+		self.users
+			.write()
+			.unwrap_or_else(|err| err.into_inner())
+			.remove(user_name);
+	}
+}
+
+impl CredentialsProvider for MemoryCredentialsProvider {
+	fn get_credentials(&self, user_name: &str) -> Option<(String, Principal)> {
+		self.users
+			.read()
+			.unwrap_or_else(|err| err.into_inner())
+			.get(user_name)
+			.cloned()
+	}
+}
+
+/// Check password by hash (argon2 or bcrypt)
+#[cfg_attr(not(any(feature = "Argon2", feature = "Bcrypt")), allow(unused_variables))]
+pub fn verify_password(password: &str, password_hash: &str) -> bool {
+	if password_hash.starts_with("$argon2") {
+		#[cfg(feature = "Argon2")]
+		{
+			use argon2::{Argon2, PasswordHash, PasswordVerifier};
+			return match PasswordHash::new(password_hash) {
+				Ok(parsed_hash) => Argon2::default()
+					.verify_password(password.as_bytes(), &parsed_hash)
+					.is_ok(),
+				Err(err) => {
+					eprintln!("Error! Bad argon2 hash: {:?}", err);
+					false
+				}
+			};
+		}
+		#[cfg(not(feature = "Argon2"))]
+		{
+			eprintln!("Error! Feature \"Argon2\" is needed for check argon2 hash");
+			return false;
+		}
+	}
+	if password_hash.starts_with("$2") {
+		#[cfg(feature = "Bcrypt")]
+		{
+			return match bcrypt::verify(password, password_hash) {
+				Ok(result) => result,
+				Err(err) => {
+					eprintln!("Error! Bad bcrypt hash: {:?}", err);
+					false
+				}
+			};
+		}
+		#[cfg(not(feature = "Bcrypt"))]
+		{
+			eprintln!("Error! Feature \"Bcrypt\" is needed for check bcrypt hash");
+			return false;
+		}
+	}
+	eprintln!("Error! Unsupported format of password hash");
+	false
+}
+
+/// Check password by hash outside of async workers (hashing is slow)
+pub fn verify_password_blocking(password: &str, password_hash: &str) -> bool {
+	match tokio::runtime::Handle::try_current() {
+		Ok(handle) if handle.runtime_flavor() == tokio::runtime::RuntimeFlavor::MultiThread =>
+			tokio::task::block_in_place(|| verify_password(password, password_hash)),
+		_ => verify_password(password, password_hash),
+	}
+}
+
+/// Authenticator by header "Authorization: Basic base64(user:password)".
+///
+/// Is intended for internal admin endpoints.
+/// Password of unknown user is checked by dummy hash,
+/// so time of response doesn't show, which users exist.
+pub struct BasicAuthenticator {
+	provider: Arc<dyn CredentialsProvider>,
+	dummy_hash: OnceLock<String>,
+}
+
+impl BasicAuthenticator {
+	pub fn new(provider: Arc<dyn CredentialsProvider>) -> BasicAuthenticator {
+		BasicAuthenticator {
+			provider,
+			dummy_hash: OnceLock::new(),
+		}
+	}
+
+	/// Specify hash for unknown users, it must have the same algorithm and cost as hashes of users.
+	///
+	/// By default hash of random password is generated by argon2 with default params
+	/// (by bcrypt with default cost without feature "Argon2").
+	pub fn set_dummy_hash(mut self, dummy_hash: &str) -> Self {
+		self.dummy_hash = OnceLock::from(dummy_hash.to_string());
+		self
+	}
+
+	//inner functions:
+	fn get_dummy_hash(&self) -> &str {
+		self.dummy_hash.get_or_init(generate_dummy_hash)
+	}
+}
+
+fn generate_dummy_hash() -> String {
+	let password = base64::encode(crate::ras_trace::random_id::<16>());
+	#[cfg(feature = "Argon2")]
+	{
+		use argon2::{Argon2, PasswordHasher, password_hash::SaltString};
+		let salt = SaltString::encode_b64(&crate::ras_trace::random_id::<16>());
+		let hash = salt.and_then(|salt| {
+			Argon2::default()
+				.hash_password(password.as_bytes(), &salt)
+				.map(|hash| hash.to_string())
+		});
+		match hash {
+			Ok(hash) => hash,
+			Err(err) => {
+				eprintln!("Error! Can't generate dummy argon2 hash: {:?}", err);
+				String::new()
+			}
+		}
+	}
+	#[cfg(all(feature = "Bcrypt", not(feature = "Argon2")))]
+	{
+		bcrypt::hash(password, bcrypt::DEFAULT_COST).unwrap_or_else(|err| {
+			eprintln!("Error! Can't generate dummy bcrypt hash: {:?}", err);
+			String::new()
+		})
+	}
+	#[cfg(not(any(feature = "Argon2", feature = "Bcrypt")))]
+	{
+		let _ = password;
+		String::new()
+	}
+}
+
+impl Authenticator for BasicAuthenticator {
+	fn authenticate(&self, request: &RequestContext) -> AuthResult {
+		let credentials = match get_authorization(request, "Basic") {
+			Some(credentials) => credentials,
+			None => return AuthResult::NoCredentials,
+		};
+		let credentials = match base64::decode(credentials)
+			.ok()
+			.and_then(|credentials| String::from_utf8(credentials).ok()) {
+			Some(credentials) => credentials,
+			None => {
+				eprintln!("Error! Bad format of Basic credentials");
+				return AuthResult::Rejected(HttpStatus::BadRequest);
+			}
+		};
+		let (user_name, password) = match credentials.split_once(':') {
+			Some(credentials) => credentials,
+			None => {
+				eprintln!("Error! Bad format of Basic credentials");
+				return AuthResult::Rejected(HttpStatus::BadRequest);
+			}
+		};
+		let (password_hash, principal) = match self.provider.get_credentials(user_name) {
+			Some((password_hash, principal)) => (password_hash, Some(principal)),
+			None => (self.get_dummy_hash().to_string(), None),
+		};
+		let verified = verify_password_blocking(password, &password_hash);
+		match principal {
+			Some(principal) if verified => AuthResult::Authenticated(principal),
+			_ => {
+				eprintln!("Error! Bad credentials of user {}", user_name);
+				AuthResult::Rejected(HttpStatus::Unauthorized)
+			}
+		}
+	}
+}
+
+#[cfg(all(test, any(feature = "Argon2", feature = "Bcrypt")))]
+mod tests {
+	use super::*;
+
+	fn check_authenticator(password_hash: &str) {
+		let provider = MemoryCredentialsProvider::new();
+		let principal = Principal {
+			user_name: "admin".to_string(),
+			user_role: 0b0000_0010,
+			scopes: Vec::new(),
+		};
+		provider.add_user(password_hash, principal.clone());
+		let authenticator = BasicAuthenticator::new(Arc::new(provider))
+			.set_dummy_hash(password_hash);
+		let request = |credentials: &str| RequestContext {
+			headers: vec![(
+				"Authorization".to_string(),
+				format!("Basic {}", base64::encode(credentials))
+			)],
+			..RequestContext::default()
+		};
+		assert!(matches!(
+			authenticator.authenticate(&request("admin:secret")),
+			AuthResult::Authenticated(ref found) if *found == principal
+		));
+		assert!(matches!(
+			authenticator.authenticate(&request("admin:wrong")),
+			AuthResult::Rejected(HttpStatus::Unauthorized)
+		));
+		// unknown user isn't authenticated by password of dummy hash
+		assert!(matches!(
+			authenticator.authenticate(&request("other:secret")),
+			AuthResult::Rejected(HttpStatus::Unauthorized)
+		));
+	}
+
+	#[cfg(feature = "Bcrypt")]
+	#[test]
+	fn basic_credentials_are_checked() {
+		check_authenticator(&bcrypt::hash("secret", 4).unwrap());
+	}
+
+	#[cfg(feature = "Argon2")]
+	#[test]
+	fn basic_credentials_are_checked_by_argon2() {
+		use argon2::{Argon2, PasswordHasher, password_hash::SaltString};
+		let salt = SaltString::encode_b64(b"some salt bytes").unwrap();
+		let password_hash = Argon2::default()
+			.hash_password(b"secret", &salt)
+			.unwrap()
+			.to_string();
+		check_authenticator(&password_hash);
+		assert!(generate_dummy_hash().starts_with("$argon2"));
+	}
+}
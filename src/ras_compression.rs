@@ -0,0 +1,241 @@
+use crate::{
+	HttpStatus,
+	RasResponse,
+};
+use std::{
+	borrow::Cow,
+	io::{Read, Write},
+};
+
+/// Content coding, available codings are selected by cargo features
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Encoding {
+	#[cfg(feature = "Gzip")]
+	Gzip,
+	#[cfg(feature = "Deflate")]
+	Deflate,
+	#[cfg(feature = "Brotli")]
+	Brotli,
+	#[cfg(feature = "Zstd")]
+	Zstd,
+}
+
+impl Encoding {
+	/// Available codings in order of preference (for equal q)
+	pub const ALL: &'static [Encoding] = &[
+		#[cfg(feature = "Brotli")]
+		Encoding::Brotli,
+		#[cfg(feature = "Zstd")]
+		Encoding::Zstd,
+		#[cfg(feature = "Gzip")]
+		Encoding::Gzip,
+		#[cfg(feature = "Deflate")]
+		Encoding::Deflate,
+	];
+
+	/// Name of coding in headers
+	pub fn get_name(&self) -> &'static str {
+		match *self {
+			#[cfg(feature = "Gzip")]
+			Encoding::Gzip => "gzip",
+			#[cfg(feature = "Deflate")]
+			Encoding::Deflate => "deflate",
+			#[cfg(feature = "Brotli")]
+			Encoding::Brotli => "br",
+			#[cfg(feature = "Zstd")]
+			Encoding::Zstd => "zstd",
+		}
+	}
+
+	pub fn from_name(name: &str) -> Option<Encoding> {
+		Encoding::ALL
+			.iter()
+			.copied()
+			.find(|encoding| encoding.get_name().eq_ignore_ascii_case(name.trim()))
+	}
+
+	pub fn compress(&self, data: &[u8]) -> std::io::Result<Vec<u8>> {
+		match *self {
+			#[cfg(feature = "Gzip")]
+			Encoding::Gzip => {
+				let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+				encoder.write_all(data)?;
+				encoder.finish()
+			},
+			#[cfg(feature = "Deflate")]
+			Encoding::Deflate => {
+				// "deflate" of HTTP is zlib format
+				let mut encoder = flate2::write::ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+				encoder.write_all(data)?;
+				encoder.finish()
+			},
+			#[cfg(feature = "Brotli")]
+			Encoding::Brotli => {
+				let mut result = Vec::new();
+				{
+					let mut encoder = brotli::CompressorWriter::new(&mut result, 4096, 5, 22);
+					encoder.write_all(data)?;
+				}
+				Ok(result)
+			},
+			#[cfg(feature = "Zstd")]
+			Encoding::Zstd => {
+				let mut encoder = zstd::stream::write::Encoder::new(Vec::new(), 3)?;
+				encoder.write_all(data)?;
+				encoder.finish()
+			},
+		}
+	}
+
+	/// Decompress data, None if result is larger than max_size
+	pub fn decompress(&self, data: &[u8], max_size: usize) -> std::io::Result<Option<Vec<u8>>> {
+		let decoder: Box<dyn Read + '_> = match *self {
+			#[cfg(feature = "Gzip")]
+			Encoding::Gzip => Box::new(flate2::read::GzDecoder::new(data)),
+			#[cfg(feature = "Deflate")]
+			Encoding::Deflate => Box::new(flate2::read::ZlibDecoder::new(data)),
+			#[cfg(feature = "Brotli")]
+			Encoding::Brotli => Box::new(brotli::Decompressor::new(data, 4096)),
+			#[cfg(feature = "Zstd")]
+			Encoding::Zstd => Box::new(zstd::stream::read::Decoder::new(data)?),
+		};
+		let mut result = Vec::new();
+		decoder.take(max_size as u64 + 1).read_to_end(&mut result)?;
+		match result.len() > max_size {
+			true => Ok(None),
+			false => Ok(Some(result)),
+		}
+	}
+}
+
+/// Select coding by header Accept-Encoding (highest q, then order of ALL)
+pub fn negotiate(accept_encoding: &str) -> Option<Encoding> {
+	let mut best: Option<(Encoding, f32)> = None;
+	for encoding in Encoding::ALL {
+		let quality = accept_encoding
+			.split(',')
+			.filter_map(|item| {
+				let mut parts = item.split(';');
+				let name = parts.next()?.trim();
+				if !name.eq_ignore_ascii_case(encoding.get_name()) && name != "*" {
+					return None;
+				}
+				let quality = parts
+					.filter_map(|param| param.trim().strip_prefix("q="))
+					.filter_map(|quality| quality.trim().parse::<f32>().ok())
+					.next()
+					.unwrap_or(1.0);
+				// exact name is preferred to "*"
+				Some((name != "*", quality))
+			})
+			.max_by(|first, second| first.0.cmp(&second.0))
+			.map(|(_, quality)| quality)
+			.unwrap_or(0.0);
+		if quality > 0.0 && best.is_none_or(|(_, best_quality)| quality > best_quality) {
+			best = Some((*encoding, quality));
+		}
+	}
+	best.map(|(encoding, _)| encoding)
+}
+
+/// Compress content of response, if it isn't less than min_size
+/// and client accepts coding
+pub fn compress_response(
+	response: RasResponse,
+	accept_encoding: Option<&str>,
+	min_size: usize,
+) -> RasResponse {
+	let content_len = response.content.as_ref().map_or(0, |content| content.len());
+	let encoded = response.headers
+		.iter()
+		.any(|(name, _)| name.eq_ignore_ascii_case("Content-Encoding"));
+	if content_len < min_size || encoded {
+		return response;
+	}
+	let mut response = response.add_header("Vary", "Accept-Encoding");
+	let encoding = match accept_encoding.and_then(negotiate) {
+		Some(encoding) => encoding,
+		None => return response,
+	};
+	let content = response.content.as_deref().unwrap_or("");
+	match encoding.compress(content.as_bytes()) {
+		Ok(encoded_content) => {
+			response.encoded_content = Some(encoded_content);
+			response.add_header("Content-Encoding", encoding.get_name())
+		},
+		Err(err) => {
+			eprintln!("Error! Can't compress response: {:?}", err);
+			response
+		},
+	}
+}
+
+/// Decompress body by header Content-Encoding.
+///
+/// Unknown coding gives 415, too large result gives 413.
+pub fn decompress_body<'a>(
+	content_encoding: Option<&str>,
+	body: &'a [u8],
+	max_size: usize,
+) -> Result<Cow<'a, [u8]>, HttpStatus> {
+	let content_encoding = match content_encoding.map(str::trim) {
+		None | Some("") => return Ok(Cow::Borrowed(body)),
+		Some(name) if name.eq_ignore_ascii_case("identity") => return Ok(Cow::Borrowed(body)),
+		Some(content_encoding) => content_encoding,
+	};
+	let encoding = match Encoding::from_name(content_encoding) {
+		Some(encoding) => encoding,
+		None => {
+			eprintln!("Error! Unsupported Content-Encoding {}", content_encoding);
+			return Err(HttpStatus::UnsupportedMediaType);
+		},
+	};
+	match encoding.decompress(body, max_size) {
+		Ok(Some(body)) => Ok(Cow::Owned(body)),
+		Ok(None) => Err(HttpStatus::PayloadTooLarge),
+		Err(err) => {
+			eprintln!("Error! Can't decompress body: {:?}", err);
+			Err(HttpStatus::BadRequest)
+		},
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn negotiation_and_round_trip() {
+		assert_eq!(negotiate("identity"), None);
+		assert_eq!(decompress_body(None, b"data", 4).unwrap().as_ref(), b"data");
+		assert_eq!(
+			decompress_body(Some("compress"), b"data", 4).unwrap_err(),
+			HttpStatus::UnsupportedMediaType
+		);
+		#[cfg(feature = "Gzip")]
+		{
+			assert_eq!(negotiate("gzip;q=0.5, *;q=0.1"), Some(Encoding::Gzip));
+			assert_eq!(negotiate("*, gzip;q=0"), Encoding::ALL.iter().copied().find(|encoding| {
+				*encoding != Encoding::Gzip
+			}));
+			let response = compress_response(
+				RasResponse::new(HttpStatus::OK, Some("a".repeat(2000))),
+				Some("gzip"),
+				1024,
+			);
+			assert!(response.headers.contains(&("Content-Encoding".to_string(), "gzip".to_string())));
+			let encoded = response.encoded_content.unwrap();
+			assert!(encoded.len() < 2000);
+			assert_eq!(decompress_body(Some("GZIP"), &encoded, 2000).unwrap(), "a".repeat(2000).as_bytes());
+			assert_eq!(
+				decompress_body(Some("gzip"), &encoded, 1999).unwrap_err(),
+				HttpStatus::PayloadTooLarge
+			);
+		}
+		for encoding in Encoding::ALL {
+			let data = b"{\"orders\": []}".repeat(100);
+			let encoded = encoding.compress(&data).unwrap();
+			assert_eq!(encoding.decompress(&encoded, data.len()).unwrap(), Some(data));
+		}
+	}
+}
@@ -0,0 +1,153 @@
+use crate::{HttpStatus, RasResponse};
+use serde::{Serialize, Deserialize};
+use std::sync::{
+	Arc,
+	atomic::{AtomicUsize, Ordering},
+};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+/// Limit of concurrent connections or requests
+#[derive(Serialize, Deserialize)]
+#[derive(Debug, Clone)]
+pub struct ConcurrencyLimit {
+	pub max_concurrent: usize,
+	/// Max count of waiting, 503 is returned beyond it
+	#[serde(default)]
+	pub max_queue: usize,
+	/// Value of header Retry-After in seconds
+	#[serde(default = "default_retry_after")]
+	pub retry_after: u64,
+}
+
+fn default_retry_after() -> u64 {
+	1
+}
+
+impl ConcurrencyLimit {
+	pub fn new(max_concurrent: usize, max_queue: usize) -> ConcurrencyLimit {
+		ConcurrencyLimit {
+			max_concurrent,
+			max_queue,
+			retry_after: default_retry_after(),
+		}
+	}
+
+	/// Specify value of header Retry-After in seconds (1 by default)
+	pub fn set_retry_after(mut self, retry_after: u64) -> Self {
+		self.retry_after = retry_after;
+		self
+	}
+}
+
+/// Limiter of concurrency with bounded queue
+pub struct ConcurrencyLimiter {
+	limit: ConcurrencyLimit,
+	semaphore: Arc<Semaphore>,
+	queued: AtomicUsize,
+}
+
+impl ConcurrencyLimiter {
+	pub fn new(limit: ConcurrencyLimit) -> ConcurrencyLimiter {
+		ConcurrencyLimiter {
+			semaphore: Arc::new(Semaphore::new(limit.max_concurrent)),
+			queued: AtomicUsize::new(0),
+			limit,
+		}
+	}
+
+	/// Take permit, wait in queue if needed.
+	///
+	/// If queue is full, response 503 with Retry-After is returned.
+	pub async fn acquire(&self) -> Result<OwnedSemaphorePermit, RasResponse> {
+		if let Ok(permit) = self.semaphore.clone().try_acquire_owned() {
+			return Ok(permit);
+		}
+		let _queue_place = match QueuePlace::take(&self.queued, self.limit.max_queue) {
+			Some(queue_place) => queue_place,
+			None => return Err(self.reject()),
+		};
+		self.semaphore
+			.clone()
+			.acquire_owned()
+			.await
+			.map_err(|_| self.reject())
+	}
+
+	/// Count of taken permits
+	pub fn in_flight(&self) -> usize {
+		self.limit.max_concurrent.saturating_sub(self.semaphore.available_permits())
+	}
+
+	/// Count of waiting in queue
+	pub fn queued(&self) -> usize {
+		self.queued.load(Ordering::SeqCst)
+	}
+
+	fn reject(&self) -> RasResponse {
+		RasResponse::new(HttpStatus::ServiceUnavailable, None)
+			.add_header("Retry-After", &self.limit.retry_after.to_string())
+	}
+}
+
+/// Take permit of limiter, if limiter is specified
+pub async fn acquire(
+	limiter: Option<&ConcurrencyLimiter>,
+) -> Result<Option<OwnedSemaphorePermit>, RasResponse> {
+	match limiter {
+		Some(limiter) => limiter.acquire().await.map(Some),
+		None => Ok(None),
+	}
+}
+
+/// Place in queue, is released on drop (also if waiting is cancelled)
+struct QueuePlace<'a> {
+	queued: &'a AtomicUsize,
+}
+
+impl<'a> QueuePlace<'a> {
+	fn take(queued: &'a AtomicUsize, max_queue: usize) -> Option<QueuePlace<'a>> {
+		queued
+			.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |count| {
+				(count < max_queue).then_some(count + 1)
+			})
+			.ok()
+			.map(|_| QueuePlace {queued})
+	}
+}
+
+impl Drop for QueuePlace<'_> {
+	fn drop(&mut self) {
+		self.queued.fetch_sub(1, Ordering::SeqCst);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn queue_is_bounded() {
+		let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+		runtime.block_on(async {
+			let limiter = Arc::new(ConcurrencyLimiter::new(
+				ConcurrencyLimit::new(1, 1).set_retry_after(5)
+			));
+			let permit = limiter.acquire().await.unwrap();
+			let waiting = tokio::spawn({
+				let limiter = limiter.clone();
+				async move {
+					limiter.acquire().await.is_ok()
+				}
+			});
+			tokio::task::yield_now().await;
+			assert_eq!(limiter.queued(), 1);
+			let rejection = limiter.acquire().await.unwrap_err();
+			assert_eq!(rejection.http_status, HttpStatus::ServiceUnavailable);
+			assert_eq!(rejection.headers, vec![("Retry-After".to_string(), "5".to_string())]);
+			drop(permit);
+			assert!(waiting.await.unwrap());
+			assert_eq!(limiter.queued(), 0);
+			assert_eq!(limiter.in_flight(), 0);
+		});
+	}
+}
@@ -0,0 +1,242 @@
+use crate::{
+	RequestLimits,
+	Timeouts,
+	ras_concurrency::ConcurrencyLimit,
+};
+use serde::{Serialize, Deserialize};
+use serde_json::Value;
+
+/// Prefix of environment variables, which override configuration
+pub const ENV_PREFIX: &str = "RAS_";
+
+/// Error of loading configuration
+#[derive(Debug)]
+pub enum ConfigError {
+	/// File can't be read
+	Io(String, std::io::Error),
+	/// Extension of file isn't json, toml or yaml (or feature of format is disabled)
+	UnsupportedFormat(String),
+	/// Bad format of file or bad values of fields
+	Parse(String),
+	/// Bad value of environment variable (name, value)
+	BadVariable(String, String),
+}
+
+impl std::fmt::Display for ConfigError {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		match self {
+			ConfigError::Io(path, err) => write!(f, "can't read configuration file {}: {}", path, err),
+			ConfigError::UnsupportedFormat(path) =>
+				write!(f, "unsupported format of configuration file {}", path),
+			ConfigError::Parse(err) => write!(f, "bad configuration: {}", err),
+			ConfigError::BadVariable(name, value) => write!(f, "bad value of {}: {}", name, value),
+		}
+	}
+}
+
+impl std::error::Error for ConfigError {}
+
+/// Result of loading configuration
+pub type ConfigResult = Result<RasConfig, ConfigError>;
+
+/// Format of configuration file
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ConfigFormat {
+	Json,
+	#[cfg(feature = "Toml")]
+	Toml,
+	#[cfg(feature = "Yaml")]
+	Yaml,
+}
+
+impl ConfigFormat {
+	/// Select format by extension of file
+	pub fn from_path(path: &str) -> Option<ConfigFormat> {
+		match std::path::Path::new(path).extension()?.to_str()? {
+			"json" => Some(ConfigFormat::Json),
+			#[cfg(feature = "Toml")]
+			"toml" => Some(ConfigFormat::Toml),
+			#[cfg(feature = "Yaml")]
+			"yaml" | "yml" => Some(ConfigFormat::Yaml),
+			_ => None,
+		}
+	}
+}
+
+/// Configuration of service.
+///
+/// Is loaded from file (json, toml with feature "Toml",
+/// yaml with feature "Yaml"), missing fields take default values.
+/// Fields are overridden by environment variables with prefix RAS_,
+/// nested fields are separated by "__".
+///
+/// # Examples
+///
+/// ```ignore
+/// // RAS_SOCKET_URL=0.0.0.0:8080 RAS_LIMITS__MAX_BODY_SIZE=65536
+/// let config = RasConfig::load(Some("service.toml"))?;
+/// RasServiceBuilder::from_config(&config, service)
+///     .add_get_function("orders".to_string(), get_orders)
+///     .run();
+/// ```
+#[derive(Serialize, Deserialize)]
+#[derive(Debug, Clone)]
+#[serde(default)]
+pub struct RasConfig {
+	/// Address for TcpListener
+	pub socket_url: String,
+	/// Count of threads of tokio runtime
+	pub num_threads: usize,
+	pub limits: RequestLimits,
+	pub timeouts: Timeouts,
+	/// Limit of concurrent connections
+	pub max_connections: Option<ConcurrencyLimit>,
+	/// Limit of requests in processing
+	pub max_requests: Option<ConcurrencyLimit>,
+	/// Life time of access token in ms
+	/// (see RasServiceBuilder::get_token_life_time)
+	pub token_life_time: u64,
+}
+
+impl Default for RasConfig {
+	fn default() -> Self {
+		RasConfig {
+			socket_url: "127.0.0.1:7777".to_string(),
+			num_threads: std::thread::available_parallelism()
+				.map(|num_threads| num_threads.get())
+				.unwrap_or(1),
+			limits: RequestLimits::default(),
+			timeouts: Timeouts::default(),
+			max_connections: None,
+			max_requests: None,
+			token_life_time: 30_000,
+		}
+	}
+}
+
+impl RasConfig {
+	//constructors:
+	/// Parse configuration
+	pub fn new_from_str(data: &str, format: ConfigFormat) -> ConfigResult {
+		let result = match format {
+			ConfigFormat::Json => serde_json::from_str(data).map_err(|err| err.to_string()),
+			#[cfg(feature = "Toml")]
+			ConfigFormat::Toml => toml::from_str(data).map_err(|err| err.to_string()),
+			#[cfg(feature = "Yaml")]
+			ConfigFormat::Yaml => serde_yaml::from_str(data).map_err(|err| err.to_string()),
+		};
+		result.map_err(ConfigError::Parse)
+	}
+
+	/// Load configuration from file, format is selected by extension
+	pub fn new_from_file(path: &str) -> ConfigResult {
+		let format = match ConfigFormat::from_path(path) {
+			Some(format) => format,
+			None => return Err(ConfigError::UnsupportedFormat(path.to_string())),
+		};
+		match std::fs::read_to_string(path) {
+			Ok(data) => RasConfig::new_from_str(&data, format),
+			Err(err) => Err(ConfigError::Io(path.to_string(), err)),
+		}
+	}
+
+	/// Load configuration from file (or default), then apply environment variables
+	pub fn load(path: Option<&str>) -> ConfigResult {
+		let config = match path {
+			Some(path) => RasConfig::new_from_file(path)?,
+			None => RasConfig::default(),
+		};
+		config.apply_env()
+	}
+
+	//interface:
+	/// Override fields by environment variables RAS_*
+	pub fn apply_env(self) -> ConfigResult {
+		self.apply_vars(std::env::vars())
+	}
+
+	/// Override fields by variables (name with prefix RAS_, value)
+	pub fn apply_vars<I>(self, vars: I) -> ConfigResult
+	where I: IntoIterator<Item = (String, String)> {
+		let mut config = serde_json::to_value(&self)
+			.map_err(|err| ConfigError::Parse(err.to_string()))?;
+		for (name, value) in vars {
+			let path = match name.strip_prefix(ENV_PREFIX) {
+				Some(path) => path.to_lowercase(),
+				None => continue,
+			};
+			let field = path
+				.split("__")
+				.try_fold(&mut config, |field, key| field.get_mut(key));
+			match field {
+				// strings are taken as is, other values are parsed as json
+				Some(field) if field.is_string() => *field = Value::String(value),
+				Some(field) => match serde_json::from_str(&value) {
+					Ok(value) => *field = value,
+					Err(_) => return Err(ConfigError::BadVariable(name, value)),
+				},
+				None => eprintln!("Error! Unknown configuration variable {}", name),
+			}
+		}
+		serde_json::from_value(config).map_err(|err| ConfigError::Parse(err.to_string()))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn file_values_are_overridden_by_env() {
+		let config = RasConfig::new_from_str(
+			r#"{"socket_url": "0.0.0.0:8080", "limits": {"max_body_size": 4096}}"#,
+			ConfigFormat::Json,
+		).unwrap();
+		assert_eq!(config.limits.max_body_size, 4096);
+		assert_eq!(config.limits.max_header_count, RequestLimits::default().max_header_count);
+		let config = config.apply_vars(vec![
+			("RAS_NUM_THREADS".to_string(), "2".to_string()),
+			("RAS_LIMITS__MAX_URI_LENGTH".to_string(), "512".to_string()),
+			("RAS_TIMEOUTS__HANDLER".to_string(), "5000".to_string()),
+			("PATH".to_string(), "/bin".to_string()),
+		]).unwrap();
+		assert_eq!(config.socket_url, "0.0.0.0:8080");
+		assert_eq!(config.num_threads, 2);
+		assert_eq!(config.limits.max_uri_length, 512);
+		assert_eq!(config.timeouts.handler, Some(std::time::Duration::from_secs(5)));
+		assert!(matches!(
+			config.clone().apply_vars(vec![("RAS_NUM_THREADS".to_string(), "many".to_string())]),
+			Err(ConfigError::BadVariable(ref name, _)) if name == "RAS_NUM_THREADS"
+		));
+		assert!(matches!(
+			RasConfig::new_from_file("service.ini"),
+			Err(ConfigError::UnsupportedFormat(_))
+		));
+		#[cfg(feature = "Authentication")]
+		{
+			let config = config
+				.apply_vars(vec![("RAS_TOKEN_LIFE_TIME".to_string(), "60000".to_string())])
+				.unwrap();
+			let builder = crate::RasServiceBuilder::from_config(&config, ());
+			assert_eq!(builder.get_token_life_time(), 60_000);
+		}
+		#[cfg(feature = "Toml")]
+		{
+			let config = RasConfig::new_from_str(
+				"token_life_time = 60000\n[limits]\nmax_header_count = 200\n",
+				ConfigFormat::Toml,
+			).unwrap();
+			assert_eq!(config.token_life_time, 60_000);
+			assert_eq!(config.limits.max_header_count, 200);
+		}
+		#[cfg(feature = "Yaml")]
+		{
+			let config = RasConfig::new_from_str(
+				"socket_url: 0.0.0.0:9090\nlimits:\n  max_body_size: 100\n",
+				ConfigFormat::Yaml,
+			).unwrap();
+			assert_eq!(config.socket_url, "0.0.0.0:9090");
+			assert_eq!(config.limits.max_body_size, 100);
+		}
+	}
+}
@@ -0,0 +1,268 @@
+use crate::{
+	HttpStatus,
+	RasResponse,
+};
+
+/// Settings of CORS.
+///
+/// Origin can be exact ("https://app.example.com"), pattern with "*"
+/// ("https://*.example.com") or "*" (any origin).
+/// Methods and headers can be "*" (any).
+///
+/// # Examples
+///
+/// ```ignore
+/// RasServiceBuilder::new(runtime, service)
+///     .set_cors(CorsConfig::new()
+///         .add_allowed_origin("https://*.example.com")
+///         .set_allowed_headers(&["Content-Type", "Authorization"])
+///         .set_allow_credentials(true)
+///         .set_max_age(600))
+/// ```
+#[derive(Debug, Clone)]
+pub struct CorsConfig {
+	allowed_origins: Vec<String>,
+	allowed_methods: Vec<String>,
+	allowed_headers: Vec<String>,
+	exposed_headers: Vec<String>,
+	allow_credentials: bool,
+	/// Seconds of caching of preflight response
+	max_age: Option<u64>,
+}
+
+impl Default for CorsConfig {
+	fn default() -> Self {
+		CorsConfig {
+			allowed_origins: Vec::new(),
+			allowed_methods: vec!["GET".to_string(), "POST".to_string()],
+			allowed_headers: vec!["Content-Type".to_string(), "Authorization".to_string()],
+			exposed_headers: Vec::new(),
+			allow_credentials: false,
+			max_age: None,
+		}
+	}
+}
+
+impl CorsConfig {
+	//constructor:
+	/// Without allowed origins, methods GET and POST,
+	/// headers Content-Type and Authorization
+	pub fn new() -> CorsConfig {
+		CorsConfig::default()
+	}
+
+	/// Allow origin (exact, pattern with "*" or "*" for any)
+	pub fn add_allowed_origin(mut self, origin: &str) -> Self {
+		self.allowed_origins.push(origin.to_string());
+		self
+	}
+
+	pub fn set_allowed_methods(mut self, methods: &[&str]) -> Self {
+		self.allowed_methods = methods.iter().map(|method| method.to_string()).collect();
+		self
+	}
+
+	pub fn set_allowed_headers(mut self, headers: &[&str]) -> Self {
+		self.allowed_headers = headers.iter().map(|header| header.to_string()).collect();
+		self
+	}
+
+	/// Response headers, which are available for scripts (for example, X-Request-Id)
+	pub fn set_exposed_headers(mut self, headers: &[&str]) -> Self {
+		self.exposed_headers = headers.iter().map(|header| header.to_string()).collect();
+		self
+	}
+
+	/// Allow cookies and Authorization (origin is returned instead of "*")
+	pub fn set_allow_credentials(mut self, allow_credentials: bool) -> Self {
+		self.allow_credentials = allow_credentials;
+		self
+	}
+
+	pub fn set_max_age(mut self, max_age: u64) -> Self {
+		self.max_age = Some(max_age);
+		self
+	}
+
+	//interface:
+	pub fn is_origin_allowed(&self, origin: &str) -> bool {
+		self.allowed_origins
+			.iter()
+			.any(|allowed| allowed == "*" || match_pattern(allowed, origin))
+	}
+
+	/// Response of preflight request (OPTIONS with Access-Control-Request-Method),
+	/// None if request isn't preflight.
+	///
+	/// Response of not allowed request hasn't CORS headers, browser rejects it.
+	pub fn preflight(&self, method: &str, headers: &[(String, String)]) -> Option<RasResponse> {
+		let origin = get_header(headers, "Origin");
+		let request_method = get_header(headers, "Access-Control-Request-Method");
+		let (origin, request_method) = match (method, origin, request_method) {
+			("OPTIONS", Some(origin), Some(request_method)) => (origin, request_method),
+			_ => return None,
+		};
+		let response = RasResponse::new(HttpStatus::OK, None).add_header("Vary", "Origin");
+		let request_headers: Vec<&str> = get_header(headers, "Access-Control-Request-Headers")
+			.map(|request_headers| request_headers
+				.split(',')
+				.map(str::trim)
+				.filter(|header| !header.is_empty())
+				.collect())
+			.unwrap_or_default();
+		if !self.is_origin_allowed(origin)
+			|| !contains(&self.allowed_methods, request_method)
+			|| !request_headers.iter().all(|header| contains(&self.allowed_headers, header)) {
+			eprintln!("Error! CORS request from {} is rejected", origin);
+			return Some(response);
+		}
+		let allowed_methods = match self.allowed_methods.iter().any(|method| method == "*") {
+			true => request_method.to_string(),
+			false => self.allowed_methods.join(", "),
+		};
+		let mut response = self.add_origin_headers(response, origin)
+			.add_header("Access-Control-Allow-Methods", &allowed_methods);
+		if !request_headers.is_empty() {
+			let allowed_headers = match self.allowed_headers.iter().any(|header| header == "*") {
+				true => request_headers.join(", "),
+				false => self.allowed_headers.join(", "),
+			};
+			response = response.add_header("Access-Control-Allow-Headers", &allowed_headers);
+		}
+		if let Some(max_age) = self.max_age {
+			response = response.add_header("Access-Control-Max-Age", &max_age.to_string());
+		}
+		Some(response)
+	}
+
+	/// Add CORS headers to response of actual request.
+	///
+	/// Vary: Origin is added to every response, if headers depend on origin
+	/// (also without origin or with rejected origin, for caches).
+	pub fn decorate(&self, origin: Option<&str>, response: RasResponse) -> RasResponse {
+		let response = match self.is_any_origin() && !self.allow_credentials {
+			true => response,
+			false => response.add_header("Vary", "Origin"),
+		};
+		let origin = match origin {
+			Some(origin) if self.is_origin_allowed(origin) => origin,
+			_ => return response,
+		};
+		let mut response = self.add_origin_headers(response, origin);
+		if !self.exposed_headers.is_empty() {
+			response = response.add_header(
+				"Access-Control-Expose-Headers",
+				&self.exposed_headers.join(", ")
+			);
+		}
+		response
+	}
+
+	//inner functions:
+	fn is_any_origin(&self) -> bool {
+		self.allowed_origins.iter().any(|allowed| allowed == "*")
+	}
+
+	fn add_origin_headers(&self, response: RasResponse, origin: &str) -> RasResponse {
+		if self.is_any_origin() && !self.allow_credentials {
+			return response.add_header("Access-Control-Allow-Origin", "*");
+		}
+		let response = response.add_header("Access-Control-Allow-Origin", origin);
+		match self.allow_credentials {
+			true => response.add_header("Access-Control-Allow-Credentials", "true"),
+			false => response,
+		}
+	}
+}
+
+/// Value of header (name is case insensitive)
+fn get_header<'a>(headers: &'a [(String, String)], name: &str) -> Option<&'a str> {
+	headers
+		.iter()
+		.find(|(header_name, _)| header_name.eq_ignore_ascii_case(name))
+		.map(|(_, value)| value.as_str())
+}
+
+/// List contains value (case insensitive) or "*"
+fn contains(list: &[String], value: &str) -> bool {
+	list.iter().any(|item| item == "*" || item.eq_ignore_ascii_case(value))
+}
+
+/// Match origin with pattern, "*" matches any chars
+fn match_pattern(pattern: &str, origin: &str) -> bool {
+	let mut parts = pattern.split('*');
+	let first = parts.next().unwrap_or("");
+	let mut rest = match origin.strip_prefix(first) {
+		Some(rest) => rest,
+		None => return false,
+	};
+	let parts: Vec<&str> = parts.collect();
+	let last = match parts.last() {
+		Some(last) => *last,
+		None => return rest.is_empty(),
+	};
+	for part in &parts[..parts.len() - 1] {
+		rest = match rest.find(part) {
+			Some(position) => &rest[position + part.len()..],
+			None => return false,
+		};
+	}
+	rest.ends_with(last)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn get_headers(response: &RasResponse, name: &str) -> Vec<String> {
+		response.headers
+			.iter()
+			.filter(|(header_name, _)| header_name == name)
+			.map(|(_, value)| value.clone())
+			.collect()
+	}
+
+	#[test]
+	fn preflight_and_actual_request() {
+		let cors = CorsConfig::new()
+			.add_allowed_origin("https://*.example.com")
+			.set_exposed_headers(&["X-Request-Id"])
+			.set_allow_credentials(true)
+			.set_max_age(600);
+		assert!(cors.is_origin_allowed("https://app.example.com"));
+		assert!(!cors.is_origin_allowed("https://example.com.evil.org"));
+		let headers = |origin: &str, request_headers: &str| vec![
+			("Origin".to_string(), origin.to_string()),
+			("Access-Control-Request-Method".to_string(), "POST".to_string()),
+			("Access-Control-Request-Headers".to_string(), request_headers.to_string()),
+		];
+		let response = cors
+			.preflight("OPTIONS", &headers("https://app.example.com", "content-type"))
+			.unwrap();
+		assert_eq!(response.http_status, HttpStatus::OK);
+		assert_eq!(get_headers(&response, "Access-Control-Allow-Origin"), ["https://app.example.com"]);
+		assert_eq!(get_headers(&response, "Access-Control-Allow-Methods"), ["GET, POST"]);
+		assert_eq!(get_headers(&response, "Access-Control-Allow-Credentials"), ["true"]);
+		assert_eq!(get_headers(&response, "Access-Control-Max-Age"), ["600"]);
+		let response = cors
+			.preflight("OPTIONS", &headers("https://app.example.com", "X-Admin"))
+			.unwrap();
+		assert!(get_headers(&response, "Access-Control-Allow-Origin").is_empty());
+		assert!(cors.preflight("GET", &headers("https://app.example.com", "")).is_none());
+
+		let response = cors.decorate(
+			Some("https://app.example.com"),
+			RasResponse::new(HttpStatus::OK, None)
+		);
+		assert_eq!(get_headers(&response, "Access-Control-Expose-Headers"), ["X-Request-Id"]);
+		assert_eq!(get_headers(&response, "Vary"), ["Origin"]);
+		let response = cors.decorate(Some("https://other.org"), RasResponse::new(HttpStatus::OK, None));
+		assert_eq!(response.headers, [("Vary".to_string(), "Origin".to_string())]);
+		let response = cors.decorate(None, RasResponse::new(HttpStatus::OK, None));
+		assert_eq!(get_headers(&response, "Vary"), ["Origin"]);
+		let response = CorsConfig::new()
+			.add_allowed_origin("*")
+			.decorate(Some("https://other.org"), RasResponse::new(HttpStatus::OK, None));
+		assert_eq!(response.headers, [("Access-Control-Allow-Origin".to_string(), "*".to_string())]);
+	}
+}
@@ -0,0 +1,338 @@
+use crate::{
+	RasResult,
+	HttpStatus,
+	Handle,
+	Arc,
+	HashMap,
+	ras_auth_client::{AccessToken, RasAuthClient, ROLE_SERVICE, ROLE_ADMINISTRATOR},
+};
+use serde::{Serialize, Deserialize, de::DeserializeOwned};
+use serde_json::Value;
+use std::sync::RwLock;
+
+/// Update of deny list
+///
+/// Time is in milliseconds since UNIX epoch, as date_spawn of AccessToken.
+#[derive(Serialize, Deserialize)]
+#[derive(Debug, Default)]
+pub struct DenyListUpdate {
+	/// Revoked tokens (kept for life time of token)
+	#[serde(default)]
+	pub token_ids: Vec<String>,
+	/// Revoked tokens with time of expiration (for example exp of JWT)
+	#[serde(default)]
+	pub tokens: Vec<DeniedToken>,
+	/// Users, whose tokens issued before time are revoked
+	#[serde(default)]
+	pub users: Vec<DeniedUser>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[derive(Debug)]
+pub struct DeniedToken {
+	pub token_id: String,
+	pub expires_at: u128,
+}
+
+#[derive(Serialize, Deserialize)]
+#[derive(Debug)]
+pub struct DeniedUser {
+	pub user_name: String,
+	pub issued_before: u128,
+	/// Time, when all denied tokens are expired (issued_before + life time of token by default)
+	#[serde(default)]
+	pub expires_at: Option<u128>,
+}
+
+/// Body of admin endpoint
+#[derive(Deserialize)]
+struct DenyListRequest {
+	token: String,
+	#[serde(flatten)]
+	update: DenyListUpdate,
+}
+
+/// In-memory deny list.
+///
+/// Entries are removed, when all tokens, denied by them, are expired.
+pub struct DenyList {
+	/// token id -> time of expiration
+	token_ids: RwLock<HashMap<String, u128>>,
+	/// user name -> (issued before, time of expiration)
+	users: RwLock<HashMap<String, (u128, u128)>>,
+	token_life_time: u128,
+}
+
+impl DenyList {
+	//constructor:
+	/// Create empty deny list.
+	///
+	/// token_life_time must be equal to get_life_time_token of RasAuthClient,
+	/// it is used for entries without time of expiration.
+	pub fn new(token_life_time: u128) -> DenyList {
+		DenyList {
+			token_ids: RwLock::new(HashMap::new()),
+			users: RwLock::new(HashMap::new()),
+			token_life_time,
+		}
+	}
+
+	//interface:
+	/// Revoke token by id until expires_at (now + token_life_time by default)
+	pub fn deny_token_id(&self, token_id: &str, expires_at: Option<u128>) {
+		let expires_at = expires_at
+			.unwrap_or_else(|| now_millis().saturating_add(self.token_life_time));
+		let mut token_ids = self.token_ids.write().unwrap_or_else(|err| err.into_inner());
+		let entry = token_ids.entry(token_id.to_string()).or_insert(expires_at);
+		*entry = (*entry).max(expires_at);
+	}
+
+	/// Revoke JWT by jti until its exp
+	pub fn deny_jwt(&self, claims: &Value) {
+		match claims["jti"].as_str() {
+			Some(token_id) => self.deny_token_id(
+				token_id,
+				claims["exp"].as_u64().map(|exp| u128::from(exp) * 1000)
+			),
+			None => eprintln!("Error! JWT hasn't claim jti for deny"),
+		}
+	}
+
+	/// Revoke all tokens of user, issued before time,
+	/// until expires_at (issued_before + token_life_time by default)
+	pub fn deny_user(&self, user_name: &str, issued_before: u128, expires_at: Option<u128>) {
+		let expires_at = expires_at
+			.unwrap_or_else(|| issued_before.saturating_add(self.token_life_time));
+		let mut users = self.users.write().unwrap_or_else(|err| err.into_inner());
+		let entry = users.entry(user_name.to_string()).or_insert((issued_before, expires_at));
+		entry.0 = entry.0.max(issued_before);
+		entry.1 = entry.1.max(expires_at);
+	}
+
+	/// Apply update (from admin endpoint or ras_auth)
+	pub fn apply_update(&self, update: DenyListUpdate) {
+		self.purge();
+		for token_id in update.token_ids {
+			self.deny_token_id(&token_id, None);
+		}
+		for token in update.tokens {
+			self.deny_token_id(&token.token_id, Some(token.expires_at));
+		}
+		for user in update.users {
+			self.deny_user(&user.user_name, user.issued_before, user.expires_at);
+		}
+	}
+
+	/// Check token
+	pub fn is_denied<C>(&self, token: &AccessToken<C>) -> bool {
+		self.is_denied_by(token.token_id.as_deref(), &token.user_name, Some(token.date_spawn))
+	}
+
+	/// Check claims of JWT: jti is token id, sub is user name,
+	/// iat (in seconds) is time of issue.
+	///
+	/// JWT of denied user without iat is denied.
+	pub fn is_jwt_denied(&self, claims: &Value) -> bool {
+		self.is_denied_by(
+			claims["jti"].as_str(),
+			claims["sub"].as_str().unwrap_or(""),
+			claims["iat"].as_u64().map(|iat| u128::from(iat) * 1000)
+		)
+	}
+
+	/// Check token by id, user name and time of issue (in ms)
+	pub fn is_denied_by(
+		&self,
+		token_id: Option<&str>,
+		user_name: &str,
+		issued_at: Option<u128>,
+	) -> bool {
+		if let Some(token_id) = token_id {
+			if self.token_ids
+				.read()
+				.unwrap_or_else(|err| err.into_inner())
+				.contains_key(token_id) {
+				return true;
+			}
+		}
+		match self.users
+			.read()
+			.unwrap_or_else(|err| err.into_inner())
+			.get(user_name) {
+			Some((issued_before, _)) => issued_at.is_none_or(|issued_at| issued_at < *issued_before),
+			None => false,
+		}
+	}
+
+	/// Remove entries, which deny only expired tokens
+	pub fn purge(&self) {
+		let now = now_millis();
+		self.token_ids
+			.write()
+			.unwrap_or_else(|err| err.into_inner())
+			.retain(|_, expires_at| now <= *expires_at);
+		self.users
+			.write()
+			.unwrap_or_else(|err| err.into_inner())
+			.retain(|_, (_, expires_at)| now <= *expires_at);
+	}
+}
+
+/// Admin endpoint for update deny list (also used for push from ras_auth).
+///
+/// Body: {"token": "access token", "token_ids": [...], "tokens": [{"token_id": ..., "expires_at": ...}],
+/// "users": [{"user_name": ..., "issued_before": ..., "expires_at": ...}]}
+///
+/// Token must have role Service or Administrator.
+///
+/// C is type of extra claims in AccessToken of service.
+///
+/// # Examples
+///
+/// ```ignore
+/// RasServiceBuilder::new(runtime, service)
+///     .add_post_function("deny_list".to_string(), update_deny_list::<Service, ()>)
+/// ```
+pub fn update_deny_list<T, C>(
+	_runtime: Handle,
+	self_service: Arc<T>,
+	query: Option<&str>,
+) -> RasResult
+where T: RasAuthClient<C>, C: DeserializeOwned {
+	let request: DenyListRequest = match query.map(serde_json::from_str) {
+		Some(Ok(request)) => request,
+		Some(Err(err)) => {
+			eprintln!("Error! Bad json format: {:?}", err);
+			return RasResult::Sync(HttpStatus::BadRequest, None);
+		},
+		None => return RasResult::Sync(HttpStatus::BadRequest, None),
+	};
+	let token = match self_service.check_and_get_access_token(&request.token) {
+		Ok(token) => token,
+		Err(_) => return RasResult::Sync(HttpStatus::Unauthorized, None),
+	};
+	if token.user_role & (ROLE_SERVICE | ROLE_ADMINISTRATOR) == 0 {
+		return RasResult::Sync(HttpStatus::Forbidden, None);
+	}
+	match self_service.get_deny_list() {
+		Some(deny_list) => {
+			deny_list.apply_update(request.update);
+			RasResult::Sync(HttpStatus::OK, None)
+		},
+		None => {
+			eprintln!("Error! Deny list isn't specified for service");
+			RasResult::Sync(HttpStatus::InternalServerError, None)
+		}
+	}
+}
+
+fn now_millis() -> u128 {
+	std::time::SystemTime::now()
+		.duration_since(std::time::UNIX_EPOCH)
+		.unwrap_or(std::time::Duration::ZERO)
+		.as_millis()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{
+		PKey,
+		ras_key_store::KeyStore,
+		ras_token_signer::TokenSigner,
+	};
+	use openssl::rsa::Rsa;
+
+	fn token(user_name: &str, date_spawn: u128, token_id: Option<&str>) -> AccessToken {
+		AccessToken {
+			user_name: user_name.to_string(),
+			user_role: 0,
+			date_spawn,
+			token_id: token_id.map(|id| id.to_string()),
+			claims: (),
+		}
+	}
+
+	#[test]
+	fn deny_by_token_id_and_user() {
+		let deny_list = DenyList::new(30_000);
+		let now = now_millis();
+		deny_list.apply_update(serde_json::from_str(&format!(r#"{{
+			"token_ids": ["revoked"],
+			"users": [{{"user_name": "bob", "issued_before": {}}}]
+		}}"#, now)).unwrap());
+		assert!(deny_list.is_denied(&token("alice", now, Some("revoked"))));
+		assert!(!deny_list.is_denied(&token("alice", now, Some("other"))));
+		assert!(deny_list.is_denied(&token("bob", now - 1, None)));
+		assert!(!deny_list.is_denied(&token("bob", now, None)));
+	}
+
+	#[test]
+	fn purge_keeps_entries_until_expiration() {
+		let deny_list = DenyList::new(1);
+		let now = now_millis();
+		let exp = now as u64 / 1000 + 3600;
+		deny_list.deny_jwt(&serde_json::json!({"jti": "jwt-1", "exp": exp}));
+		deny_list.deny_token_id("token-1", None);
+		deny_list.deny_user("bob", now, Some(u128::from(exp) * 1000));
+		deny_list.deny_user("carol", now, None);
+		std::thread::sleep(std::time::Duration::from_millis(5));
+		deny_list.purge();
+		let jwt_claims = serde_json::json!({"jti": "jwt-1", "sub": "alice", "exp": exp});
+		assert!(deny_list.is_jwt_denied(&jwt_claims));
+		assert!(!deny_list.is_denied(&token("alice", now, Some("token-1"))));
+		assert!(deny_list.is_jwt_denied(&serde_json::json!({"sub": "bob", "iat": now as u64 / 1000 - 1})));
+		assert!(!deny_list.is_denied(&token("carol", now - 1, None)));
+	}
+
+	struct Service {
+		key_store: KeyStore,
+		deny_list: DenyList,
+	}
+
+	impl RasAuthClient for Service {
+		fn get_key_store(&self) -> Option<&KeyStore> {
+			Some(&self.key_store)
+		}
+
+		fn get_deny_list(&self) -> Option<&DenyList> {
+			Some(&self.deny_list)
+		}
+	}
+
+	#[test]
+	fn revoked_tokens_are_rejected_by_client() {
+		let private_key = PKey::from_rsa(Rsa::generate(2048).unwrap()).unwrap();
+		let service = Service {
+			key_store: KeyStore::new(),
+			deny_list: DenyList::new(30_000),
+		};
+		service.key_store.add_key(
+			"key",
+			PKey::public_key_from_pem(&private_key.public_key_to_pem().unwrap()).unwrap()
+		);
+		let signer = TokenSigner::new(private_key).set_key_id("key");
+		let mut access_token = AccessToken::new("alice".to_string(), 0);
+		access_token.token_id = Some("token-1".to_string());
+		let token_str = signer.sign(&access_token).unwrap();
+		let now = now_millis() as u64 / 1000;
+		let jwt = |jti: &str, sub: &str| signer.sign_jwt(&serde_json::json!({
+			"jti": jti,
+			"sub": sub,
+			"iat": now,
+			"exp": now + 30,
+		})).unwrap();
+		assert!(service.check_and_get_access_token(&token_str).is_ok());
+		assert!(service.check_and_get_jwt_claims(&jwt("token-2", "bob")).is_ok());
+
+		service.deny_list.deny_token_id("token-1", None);
+		service.deny_list.deny_token_id("token-2", None);
+		assert!(service.check_and_get_access_token(&token_str).is_err());
+		assert!(service.check_and_get_jwt_claims(&jwt("token-2", "bob")).is_err());
+		assert!(service.check_and_get_jwt_claims(&jwt("token-3", "bob")).is_ok());
+
+		service.deny_list.deny_user("bob", (u128::from(now) + 1) * 1000, None);
+		assert!(service.check_and_get_jwt_claims(&jwt("token-3", "bob")).is_err());
+		assert!(service.check_and_get_jwt_claims(&jwt("token-3", "carol")).is_ok());
+	}
+}
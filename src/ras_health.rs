@@ -0,0 +1,204 @@
+use crate::{
+	Arc,
+	HttpStatus,
+	RasResponse,
+};
+use serde_json::{json, Map, Value};
+use std::{
+	future::Future,
+	pin::Pin,
+	sync::atomic::{AtomicBool, Ordering},
+	time::Duration,
+};
+
+/// Max time of readiness check, slower check is failed
+pub const CHECK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Result of readiness check, error is shown in status
+pub type CheckFuture = Pin<Box<dyn Future<Output = Result<(), String>> + Send>>;
+
+type ReadinessCheck<T> = Arc<dyn Fn(Arc<T>) -> CheckFuture + Send + Sync>;
+
+/// Liveness and readiness of service.
+///
+/// Service is ready, if it isn't shutting down and all checks are passed.
+///
+/// # Examples
+///
+/// ```ignore
+/// RasServiceBuilder::new(runtime, service)
+///     .set_health_paths("/healthz", "/readyz")
+///     .add_readiness_check("db", |service: Arc<Service>| async move {
+///         service.db.ping().await.map_err(|err| err.to_string())
+///     })
+///     .set_graceful_shutdown(GracefulShutdown::new(
+///         Duration::from_secs(5),
+///         Duration::from_secs(30),
+///     ))
+/// ```
+pub struct Health<T> {
+	checks: Vec<(String, ReadinessCheck<T>)>,
+	shutting_down: AtomicBool,
+}
+
+impl<T> Default for Health<T> {
+	fn default() -> Self {
+		Health {
+			checks: Vec::new(),
+			shutting_down: AtomicBool::new(false),
+		}
+	}
+}
+
+impl<T> Health<T>
+where T: Sync + Send + 'static {
+	//constructor:
+	pub fn new() -> Health<T> {
+		Health::default()
+	}
+
+	//interface:
+	pub fn add_check<F, R>(&mut self, name: &str, check: F)
+	where
+		F: Fn(Arc<T>) -> R + Send + Sync + 'static,
+		R: Future<Output = Result<(), String>> + Send + 'static {
+		let check: ReadinessCheck<T> = Arc::new(move |service| Box::pin(check(service)));
+		self.checks.push((name.to_string(), check));
+	}
+
+	/// Mark service as shutting down, readiness becomes false
+	pub fn set_shutting_down(&self) {
+		self.shutting_down.store(true, Ordering::SeqCst);
+	}
+
+	pub fn is_shutting_down(&self) -> bool {
+		self.shutting_down.load(Ordering::SeqCst)
+	}
+
+	/// Response of liveness probe
+	pub fn liveness(&self) -> RasResponse {
+		RasResponse::new(HttpStatus::OK, Some(json!({"status": "ok"}).to_string()))
+	}
+
+	/// Response of readiness probe, checks are run concurrently
+	pub async fn readiness(&self, service: Arc<T>) -> RasResponse {
+		let handles: Vec<_> = self.checks
+			.iter()
+			.map(|(name, check)| {
+				let check = check(service.clone());
+				(name, tokio::spawn(tokio::time::timeout(CHECK_TIMEOUT, check)))
+			})
+			.collect();
+		let mut ready = true;
+		let mut checks = Map::new();
+		for (name, handle) in handles {
+			let result = match handle.await {
+				Ok(Ok(result)) => result,
+				Ok(Err(_)) => Err("timeout".to_string()),
+				Err(err) => Err(format!("check is failed: {}", err)),
+			};
+			let status = match result {
+				Ok(()) => json!({"status": "ok"}),
+				Err(err) => {
+					eprintln!("Error! Readiness check {} is failed: {}", name, err);
+					ready = false;
+					json!({"status": "fail", "error": err})
+				},
+			};
+			checks.insert(name.clone(), status);
+		}
+		let (http_status, status) = if self.is_shutting_down() {
+			(HttpStatus::ServiceUnavailable, "shutting_down")
+		} else if ready {
+			(HttpStatus::OK, "ok")
+		} else {
+			(HttpStatus::ServiceUnavailable, "fail")
+		};
+		let content = json!({
+			"status": status,
+			"checks": Value::Object(checks),
+		});
+		RasResponse::new(http_status, Some(content.to_string()))
+	}
+}
+
+/// Settings of graceful shutdown by SIGTERM or Ctrl+C
+#[derive(Debug, Clone)]
+pub struct GracefulShutdown {
+	/// Time between signal and stop of accepting connections,
+	/// readiness is false already (for balancer notices it)
+	pub delay: Duration,
+	/// Max time of waiting of opened connections
+	pub timeout: Duration,
+}
+
+impl GracefulShutdown {
+	pub fn new(delay: Duration, timeout: Duration) -> GracefulShutdown {
+		GracefulShutdown {
+			delay,
+			timeout,
+		}
+	}
+}
+
+/// Wait SIGTERM (on unix) or Ctrl+C
+pub async fn shutdown_signal() {
+	#[cfg(unix)]
+	{
+		use tokio::signal::unix::{signal, SignalKind};
+		match signal(SignalKind::terminate()) {
+			Ok(mut terminate) => {
+				tokio::select! {
+					_ = tokio::signal::ctrl_c() => (),
+					_ = terminate.recv() => (),
+				}
+				return;
+			},
+			Err(err) => eprintln!("Error! Can't listen SIGTERM: {:?}", err),
+		}
+	}
+	if let Err(err) = tokio::signal::ctrl_c().await {
+		eprintln!("Error! Can't listen Ctrl+C: {:?}", err);
+		std::future::pending::<()>().await;
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	struct Service {
+		db_is_up: bool,
+	}
+
+	#[test]
+	fn readiness_is_aggregated() {
+		let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+		runtime.block_on(async {
+			let mut health = Health::new();
+			health.add_check("db", |service: Arc<Service>| async move {
+				match service.db_is_up {
+					true => Ok(()),
+					false => Err("connection refused".to_string()),
+				}
+			});
+			let response = health.readiness(Arc::new(Service {db_is_up: true})).await;
+			assert_eq!(response.http_status, HttpStatus::OK);
+			assert_eq!(
+				response.content.unwrap(),
+				r#"{"checks":{"db":{"status":"ok"}},"status":"ok"}"#
+			);
+			let response = health.readiness(Arc::new(Service {db_is_up: false})).await;
+			assert_eq!(response.http_status, HttpStatus::ServiceUnavailable);
+			assert_eq!(
+				response.content.unwrap(),
+				r#"{"checks":{"db":{"error":"connection refused","status":"fail"}},"status":"fail"}"#
+			);
+			health.set_shutting_down();
+			let response = health.readiness(Arc::new(Service {db_is_up: true})).await;
+			assert_eq!(response.http_status, HttpStatus::ServiceUnavailable);
+			assert!(response.content.unwrap().contains(r#""status":"shutting_down""#));
+			assert_eq!(health.liveness().http_status, HttpStatus::OK);
+		});
+	}
+}
@@ -0,0 +1,179 @@
+use crate::{HttpStatus, RasResponse, RequestLimits, Timeouts};
+use bytes::Bytes;
+use std::future::Future;
+use tokio::io::{AsyncRead, AsyncWrite};
+
+/// Connection preface of HTTP/2 (h2c with prior knowledge starts by it)
+pub const PREFACE: &[u8] = b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n";
+
+/// Request of HTTP/2 stream
+pub struct Http2Request {
+	pub method: String,
+	/// Path with GET-parameters
+	pub path: String,
+	pub headers: Vec<(String, String)>,
+	pub body: Vec<u8>,
+}
+
+/// Serve HTTP/2 connection.
+///
+/// Every stream is handled in own task by handler.
+/// Timeout of reading headers is applied to handshake.
+pub async fn serve_connection<S, H, F>(
+	stream: S,
+	limits: RequestLimits,
+	timeouts: Timeouts,
+	handler: H,
+)
+where
+	S: AsyncRead + AsyncWrite + Unpin,
+	H: Fn(Http2Request) -> F + Clone + Send + 'static,
+	F: Future<Output = RasResponse> + Send {
+	let max_body_size = limits.max_body_size;
+	let handshake = h2::server::Builder::new()
+		.max_header_list_size(limits.max_header_bytes.try_into().unwrap_or(u32::MAX))
+		.handshake(stream);
+	let handshake = match timeouts.read_header {
+		Some(timeout) => match tokio::time::timeout(timeout, handshake).await {
+			Ok(handshake) => handshake,
+			Err(_) => {
+				eprintln!("Error! Timeout of HTTP/2 handshake");
+				return;
+			}
+		},
+		None => handshake.await,
+	};
+	let mut connection = match handshake {
+		Ok(connection) => connection,
+		Err(err) => {
+			eprintln!("Error! HTTP/2 handshake failed: {:?}", err);
+			return;
+		}
+	};
+	while let Some(result) = connection.accept().await {
+		let (request, respond) = match result {
+			Ok(val) => val,
+			Err(err) => {
+				eprintln!("Error! Can't accept HTTP/2 stream: {:?}", err);
+				return;
+			}
+		};
+		let handler = handler.clone();
+		let read_body_timeout = timeouts.read_body;
+		tokio::spawn(async move {
+			let (parts, body) = request.into_parts();
+			let body = match read_body_timeout {
+				Some(timeout) => tokio::time::timeout(timeout, read_body(body, max_body_size))
+					.await
+					.unwrap_or(Err(HttpStatus::RequestTimeout)),
+				None => read_body(body, max_body_size).await,
+			};
+			let response = match body {
+				Ok(body) => handler(Http2Request {
+					method: parts.method.to_string(),
+					path: parts.uri
+						.path_and_query()
+						.map(|path| path.to_string())
+						.unwrap_or_else(|| "/".to_string()),
+					headers: parts.headers
+						.iter()
+						.map(|(name, value)| (
+							name.to_string(),
+							String::from_utf8_lossy(value.as_bytes()).to_string()
+						))
+						.collect(),
+					body,
+				}).await,
+				Err(http_status) => RasResponse::new(http_status, None),
+			};
+			send_response(respond, response);
+		});
+	}
+}
+
+async fn read_body(mut body: h2::RecvStream, max_size: usize) -> Result<Vec<u8>, HttpStatus> {
+	let mut data = Vec::new();
+	while let Some(chunk) = body.data().await {
+		let chunk = match chunk {
+			Ok(chunk) => chunk,
+			Err(err) => {
+				eprintln!("Error! Can't read HTTP/2 body: {:?}", err);
+				return Err(HttpStatus::BadRequest);
+			}
+		};
+		if data.len() + chunk.len() > max_size {
+			return Err(HttpStatus::PayloadTooLarge);
+		}
+		let _ = body.flow_control().release_capacity(chunk.len());
+		data.extend_from_slice(&chunk);
+	}
+	Ok(data)
+}
+
+fn send_response(mut respond: h2::server::SendResponse<Bytes>, mut response: RasResponse) {
+	let content = response.take_body();
+	let mut builder = http::Response::builder()
+		.status(response.http_status.get_code())
+		.header("content-length", content.len())
+		.header("content-type", response.content_type);
+	for (name, value) in response.headers {
+		builder = builder.header(name, value);
+	}
+	let response = match builder.body(()) {
+		Ok(response) => response,
+		Err(err) => {
+			eprintln!("Error! Can't build HTTP/2 response: {:?}", err);
+			return;
+		}
+	};
+	let mut send_stream = match respond.send_response(response, content.is_empty()) {
+		Ok(send_stream) => send_stream,
+		Err(err) => {
+			eprintln!("Error! Can't send data: {:?}", err);
+			return;
+		}
+	};
+	if content.is_empty() {
+		return;
+	}
+	if let Err(err) = send_stream.send_data(Bytes::from(content), true) {
+		eprintln!("Error! Can't send data: {:?}", err);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn request_is_dispatched_by_stream() {
+		let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+		runtime.block_on(async {
+			let (client_stream, server_stream) = tokio::io::duplex(16 * 1024);
+			let limits = RequestLimits::default();
+			let timeouts = Timeouts::default();
+			tokio::spawn(serve_connection(server_stream, limits, timeouts, |request: Http2Request| async move {
+				let content = format!(
+					"{} {} {}",
+					request.method,
+					request.path,
+					String::from_utf8(request.body).unwrap()
+				);
+				RasResponse::new(HttpStatus::OK, Some(content))
+			}));
+			let (mut client, connection) = h2::client::handshake(client_stream).await.unwrap();
+			tokio::spawn(connection);
+			let request = http::Request::builder()
+				.method("POST")
+				.uri("http://localhost/api/echo?id=1")
+				.body(())
+				.unwrap();
+			let (response, mut send_stream) = client.send_request(request, false).unwrap();
+			send_stream.send_data(Bytes::from("{}"), true).unwrap();
+			let response = response.await.unwrap();
+			assert_eq!(response.status(), 200);
+			let content = read_body(response.into_body(), usize::MAX).await.unwrap();
+			assert_eq!(content, b"POST /api/echo?id=1 {}");
+		});
+	}
+}
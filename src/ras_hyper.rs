@@ -0,0 +1,306 @@
+use crate::{
+	Arc,
+	HttpStatus,
+	RasResponse,
+	RasServiceBuilder,
+	RequestContext,
+};
+use hyper::{
+	Body,
+	body::HttpBody,
+	Request,
+	Response,
+	header::{CONTENT_LENGTH, CONTENT_TYPE},
+	server::conn::Http,
+	service::Service,
+};
+use std::{
+	convert::Infallible,
+	future::Future,
+	io,
+	pin::Pin,
+	task::{Context, Poll},
+	time::Duration,
+};
+use tokio::{
+	io::{AsyncRead, AsyncWrite, ReadBuf},
+	time::Sleep,
+};
+
+/// Service of hyper, which dispatches requests
+/// to GET and POST functions of RasServiceBuilder.
+pub struct RasHyperService<T> {
+	builder: Arc<RasServiceBuilder<T>>,
+	/// Context of connection (peer address, client certificate)
+	context: RequestContext,
+}
+
+impl<T> RasHyperService<T> {
+	pub fn new(builder: Arc<RasServiceBuilder<T>>, context: RequestContext) -> RasHyperService<T> {
+		RasHyperService {
+			builder,
+			context,
+		}
+	}
+}
+
+impl<T> Service<Request<Body>> for RasHyperService<T>
+where T: Sync + Send + 'static {
+	type Response = Response<Body>;
+	type Error = Infallible;
+	type Future = Pin<Box<dyn Future<Output = Result<Response<Body>, Infallible>> + Send>>;
+
+	fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Infallible>> {
+		Poll::Ready(Ok(()))
+	}
+
+	fn call(&mut self, request: Request<Body>) -> Self::Future {
+		let builder = self.builder.clone();
+		let context = self.context.clone();
+		Box::pin(async move {
+			let (parts, body) = request.into_parts();
+			let max_body_size = builder.request_limits.max_body_size;
+			let body = match builder.timeouts.read_body {
+				Some(timeout) => tokio::time::timeout(timeout, read_body(body, max_body_size))
+					.await
+					.unwrap_or(Err(HttpStatus::RequestTimeout)),
+				None => read_body(body, max_body_size).await,
+			};
+			let response = match body {
+				Ok(body) => {
+					let path = parts.uri
+						.path_and_query()
+						.map(|path| path.to_string())
+						.unwrap_or_else(|| "/".to_string());
+					let headers = parts.headers
+						.iter()
+						.map(|(name, value)| (
+							name.to_string(),
+							String::from_utf8_lossy(value.as_bytes()).to_string()
+						))
+						.collect();
+					builder.request_handler(
+						parts.method.as_str(),
+						&path,
+						headers,
+						&body,
+						context,
+					).await
+				},
+				Err(http_status) => RasResponse::new(http_status, None),
+			};
+			Ok(get_response(response))
+		})
+	}
+}
+
+/// Serve connection by hyper (HTTP/1.1 with keep-alive and HTTP/2).
+///
+/// If http2_only, connection is served only as HTTP/2 (ALPN "h2").
+///
+/// Too large request line or headers are rejected by hyper (414 and 431),
+/// connection is closed, if headers aren't read before timeout
+/// or if sending of response is blocked longer than write timeout.
+pub async fn serve_connection<T, S>(
+	builder: Arc<RasServiceBuilder<T>>,
+	stream: S,
+	context: RequestContext,
+	http2_only: bool,
+)
+where
+	T: Sync + Send + 'static,
+	S: AsyncRead + AsyncWrite + Unpin + Send + 'static {
+	let max_header_bytes = builder.request_limits.max_header_bytes;
+	let mut http = Http::new();
+	if let Some(timeout) = builder.timeouts.read_header {
+		http.http1_header_read_timeout(timeout);
+	}
+	let stream = WriteTimeoutStream::new(stream, builder.timeouts.write);
+	let service = RasHyperService::new(builder, context);
+	if let Err(err) = http
+		.http2_only(http2_only)
+		// hyper requires at least 8 KiB
+		.max_buf_size(max_header_bytes.max(8192))
+		.http2_max_header_list_size(max_header_bytes.try_into().unwrap_or(u32::MAX))
+		.serve_connection(stream, service)
+		.await {
+		eprintln!("Error! Can't serve connection: {:?}", err);
+	}
+}
+
+/// Stream, which fails writing, if it is blocked longer than timeout
+struct WriteTimeoutStream<S> {
+	stream: S,
+	timeout: Option<Duration>,
+	deadline: Option<Pin<Box<Sleep>>>,
+}
+
+impl<S> WriteTimeoutStream<S> {
+	fn new(stream: S, timeout: Option<Duration>) -> WriteTimeoutStream<S> {
+		WriteTimeoutStream {
+			stream,
+			timeout,
+			deadline: None,
+		}
+	}
+
+	/// Reset deadline after progress or check it, if writing is blocked
+	fn check_timeout<R>(&mut self, cx: &mut Context<'_>, poll: Poll<io::Result<R>>) -> Poll<io::Result<R>> {
+		let timeout = match (poll.is_ready(), self.timeout) {
+			(false, Some(timeout)) => timeout,
+			_ => {
+				self.deadline = None;
+				return poll;
+			}
+		};
+		let deadline = self.deadline
+			.get_or_insert_with(|| Box::pin(tokio::time::sleep(timeout)));
+		match deadline.as_mut().poll(cx) {
+			Poll::Ready(()) => {
+				eprintln!("Error! Timeout of sending data");
+				Poll::Ready(Err(io::ErrorKind::TimedOut.into()))
+			},
+			Poll::Pending => Poll::Pending,
+		}
+	}
+}
+
+impl<S: AsyncRead + Unpin> AsyncRead for WriteTimeoutStream<S> {
+	fn poll_read(
+		mut self: Pin<&mut Self>,
+		cx: &mut Context<'_>,
+		buf: &mut ReadBuf<'_>,
+	) -> Poll<io::Result<()>> {
+		Pin::new(&mut self.stream).poll_read(cx, buf)
+	}
+}
+
+impl<S: AsyncWrite + Unpin> AsyncWrite for WriteTimeoutStream<S> {
+	fn poll_write(
+		mut self: Pin<&mut Self>,
+		cx: &mut Context<'_>,
+		buf: &[u8],
+	) -> Poll<io::Result<usize>> {
+		let poll = Pin::new(&mut self.stream).poll_write(cx, buf);
+		self.check_timeout(cx, poll)
+	}
+
+	fn poll_write_vectored(
+		mut self: Pin<&mut Self>,
+		cx: &mut Context<'_>,
+		bufs: &[io::IoSlice<'_>],
+	) -> Poll<io::Result<usize>> {
+		let poll = Pin::new(&mut self.stream).poll_write_vectored(cx, bufs);
+		self.check_timeout(cx, poll)
+	}
+
+	fn is_write_vectored(&self) -> bool {
+		self.stream.is_write_vectored()
+	}
+
+	fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+		let poll = Pin::new(&mut self.stream).poll_flush(cx);
+		self.check_timeout(cx, poll)
+	}
+
+	fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+		let poll = Pin::new(&mut self.stream).poll_shutdown(cx);
+		self.check_timeout(cx, poll)
+	}
+}
+
+async fn read_body(mut body: Body, max_size: usize) -> Result<Vec<u8>, HttpStatus> {
+	if body.size_hint().lower() > max_size as u64 {
+		return Err(HttpStatus::PayloadTooLarge);
+	}
+	let mut data = Vec::new();
+	while let Some(chunk) = body.data().await {
+		let chunk = match chunk {
+			Ok(chunk) => chunk,
+			Err(err) => {
+				eprintln!("Error! Can't read body: {:?}", err);
+				return Err(HttpStatus::BadRequest);
+			}
+		};
+		if data.len() + chunk.len() > max_size {
+			return Err(HttpStatus::PayloadTooLarge);
+		}
+		data.extend_from_slice(&chunk);
+	}
+	Ok(data)
+}
+
+fn get_response(mut response: RasResponse) -> Response<Body> {
+	let content = response.take_body();
+	let mut builder = Response::builder()
+		.status(response.http_status.get_code())
+		.header(CONTENT_LENGTH, content.len())
+		.header(CONTENT_TYPE, response.content_type);
+	for (name, value) in response.headers {
+		builder = builder.header(name, value);
+	}
+	builder
+		.body(Body::from(content))
+		.unwrap_or_else(|err| {
+			eprintln!("Error! Can't build response: {:?}", err);
+			let mut response = Response::new(Body::empty());
+			*response.status_mut() = hyper::StatusCode::INTERNAL_SERVER_ERROR;
+			response
+		})
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+	struct Service {}
+
+	fn hello(
+		_runtime: crate::Handle,
+		_service: Arc<Service>,
+		params: Option<&str>,
+	) -> crate::RasResult {
+		crate::RasResult::Sync(HttpStatus::OK, params.map(|params| params.to_string()))
+	}
+
+	#[test]
+	fn keep_alive_connection() {
+		let runtime = RasServiceBuilder::<Service>::get_runtime(1);
+		let builder = Arc::new(RasServiceBuilder::new(runtime, Service {})
+			.add_get_function("hello".to_string(), hello));
+		builder.clone().runtime.block_on(async move {
+			let (mut client_stream, server_stream) = tokio::io::duplex(16 * 1024);
+			tokio::spawn(serve_connection(builder, server_stream, RequestContext::default(), false));
+			client_stream.write_all(concat!(
+				"GET /api/hello?first HTTP/1.1\r\nHost: localhost\r\n\r\n",
+				"GET /api/hello?second HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n",
+			).as_bytes()).await.unwrap();
+			let mut response = String::new();
+			client_stream.read_to_string(&mut response).await.unwrap();
+			assert_eq!(response.matches("HTTP/1.1 200 OK").count(), 2);
+			assert!(response.contains("\r\n\r\nfirst"));
+			assert!(response.ends_with("\r\n\r\nsecond"));
+		});
+	}
+
+	#[test]
+	fn write_timeout_closes_connection() {
+		let runtime = RasServiceBuilder::<Service>::get_runtime(1);
+		let builder = Arc::new(RasServiceBuilder::new(runtime, Service {})
+			.add_get_function("hello".to_string(), hello)
+			.set_write_timeout(Some(Duration::from_millis(100))));
+		builder.clone().runtime.block_on(async move {
+			// client doesn't read response, which is larger than buffer
+			let (mut client_stream, server_stream) = tokio::io::duplex(256);
+			let connection = tokio::spawn(
+				serve_connection(builder, server_stream, RequestContext::default(), false)
+			);
+			client_stream.write_all(format!(
+				"GET /api/hello?{} HTTP/1.1\r\nHost: localhost\r\n\r\n",
+				"a".repeat(4096),
+			).as_bytes()).await.unwrap();
+			assert!(tokio::time::timeout(Duration::from_secs(5), connection).await.is_ok());
+		});
+	}
+}
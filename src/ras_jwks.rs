@@ -0,0 +1,458 @@
+use crate::{
+	Verifier,
+	MessageDigest,
+	PKey,
+	Public,
+};
+use openssl::{
+	bn::BigNum,
+	ec::{EcGroup, EcKey},
+	ecdsa::EcdsaSig,
+	nid::Nid,
+	pkey::Id,
+	rsa::{Padding, Rsa},
+	sign::RsaPssSaltlen,
+};
+use serde::{Serialize, Deserialize};
+use serde_json::Value;
+use reqwest::Client;
+
+/// JSON Web Key (RFC 7517).
+///
+/// Supported key types: RSA, EC (P-256, P-384, P-521), OKP (Ed25519, Ed448).
+#[derive(Serialize, Deserialize)]
+#[derive(Debug, Clone, Default)]
+pub struct Jwk {
+	pub kty: String,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub kid: Option<String>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub alg: Option<String>,
+	#[serde(rename = "use", skip_serializing_if = "Option::is_none")]
+	pub key_use: Option<String>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub crv: Option<String>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub n: Option<String>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub e: Option<String>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub x: Option<String>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub y: Option<String>,
+}
+
+impl Jwk {
+	/// Convert JWK to public key.
+	pub fn to_public_key(&self) -> Result<PKey<Public>, ()> {
+		let result = match self.kty.as_str() {
+			"RSA" => {
+				let n = BigNum::from_slice(&decode_b64url_field(&self.n, "n")?);
+				let e = BigNum::from_slice(&decode_b64url_field(&self.e, "e")?);
+				match (n, e) {
+					(Ok(n), Ok(e)) => Rsa::from_public_components(n, e)
+						.and_then(PKey::from_rsa),
+					_ => {
+						eprintln!("Error! Bad RSA components in JWK");
+						return Err(());
+					}
+				}
+			},
+			"EC" => {
+				let nid = match self.crv.as_deref() {
+					Some("P-256") => Nid::X9_62_PRIME256V1,
+					Some("P-384") => Nid::SECP384R1,
+					Some("P-521") => Nid::SECP521R1,
+					crv => {
+						eprintln!("Error! Unsupported EC curve in JWK: {:?}", crv);
+						return Err(());
+					}
+				};
+				let x = BigNum::from_slice(&decode_b64url_field(&self.x, "x")?);
+				let y = BigNum::from_slice(&decode_b64url_field(&self.y, "y")?);
+				match (EcGroup::from_curve_name(nid), x, y) {
+					(Ok(group), Ok(x), Ok(y)) =>
+						EcKey::from_public_key_affine_coordinates(&group, &x, &y)
+							.and_then(PKey::from_ec_key),
+					_ => {
+						eprintln!("Error! Bad EC components in JWK");
+						return Err(());
+					}
+				}
+			},
+			"OKP" => {
+				let id = match self.crv.as_deref() {
+					Some("Ed25519") => Id::ED25519,
+					Some("Ed448") => Id::ED448,
+					crv => {
+						eprintln!("Error! Unsupported OKP curve in JWK: {:?}", crv);
+						return Err(());
+					}
+				};
+				PKey::public_key_from_raw_bytes(&decode_b64url_field(&self.x, "x")?, id)
+			},
+			kty => {
+				eprintln!("Error! Unsupported key type in JWK: {}", kty);
+				return Err(());
+			}
+		};
+		result.map_err(|err| {
+			eprintln!("Error! Can't create public key from JWK: {:?}", err);
+		})
+	}
+}
+
+/// JSON Web Key Set.
+#[derive(Serialize, Deserialize)]
+#[derive(Debug, Clone, Default)]
+pub struct JwkSet {
+	pub keys: Vec<Jwk>,
+}
+
+impl JwkSet {
+	pub fn new_from_str(json: &str) -> Result<JwkSet, ()> {
+		serde_json::from_str(json).map_err(|err| {
+			eprintln!("Error! Bad JWKS format: {:?}", err);
+		})
+	}
+
+	/// Load JWKS from local file.
+	pub async fn new_from_file(path: &str) -> Result<JwkSet, ()> {
+		match tokio::fs::read_to_string(path).await {
+			Ok(json) => JwkSet::new_from_str(&json),
+			Err(err) => {
+				eprintln!("Error! Can't read JWKS file {}: {:?}", path, err);
+				Err(())
+			}
+		}
+	}
+
+	/// Load JWKS from url (for example, jwks_uri of OIDC issuer).
+	pub async fn new_from_url(client: &Client, url: &str) -> Result<JwkSet, ()> {
+		let response = match client.get(url).send().await {
+			Ok(response) => response,
+			Err(err) => {
+				eprintln!("Error! Can't get JWKS from {}: {:?}", url, err);
+				return Err(());
+			}
+		};
+		if !response.status().is_success() {
+			eprintln!("Error! Can't get JWKS from {}: {}", url, response.status());
+			return Err(());
+		}
+		match response.text().await {
+			Ok(json) => JwkSet::new_from_str(&json),
+			Err(err) => {
+				eprintln!("Error! Can't read JWKS from {}: {:?}", url, err);
+				Err(())
+			}
+		}
+	}
+
+	/// Public keys for verification signs with their key ids.
+	///
+	/// Keys for encryption and keys of unsupported types are skipped.
+	pub fn get_public_keys(&self) -> Vec<(Option<String>, PKey<Public>)> {
+		self.get_public_keys_with_alg()
+			.into_iter()
+			.map(|(key_id, _, key)| (key_id, key))
+			.collect()
+	}
+
+	/// Public keys for verification signs with their key ids and algorithms (alg of JWK).
+	pub fn get_public_keys_with_alg(&self) -> Vec<(Option<String>, Option<String>, PKey<Public>)> {
+		self.keys
+			.iter()
+			.filter(|jwk| jwk.key_use.as_deref().unwrap_or("sig") == "sig")
+			.filter_map(|jwk| jwk.to_public_key()
+				.ok()
+				.map(|key| (jwk.kid.clone(), jwk.alg.clone(), key)))
+			.collect()
+	}
+}
+
+/// Header of JWT.
+#[derive(Serialize, Deserialize)]
+#[derive(Debug, Clone)]
+pub struct JwtHeader {
+	pub alg: String,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub kid: Option<String>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub typ: Option<String>,
+}
+
+/// Parts of JWT (compact JWS), signature isn't verified.
+pub struct JwtParts<'a> {
+	pub header: JwtHeader,
+	pub claims: Value,
+	/// header.payload, which is signed
+	pub signed_data: &'a str,
+	pub sign: Vec<u8>,
+}
+
+impl<'a> JwtParts<'a> {
+	pub fn new_from_str(token: &'a str) -> Result<JwtParts<'a>, ()> {
+		let sign_start = match token.rfind('.') {
+			Some(sign_start) => sign_start,
+			None => return Err(()),
+		};
+		let signed_data = &token[..sign_start];
+		let mut parts = signed_data.split('.');
+		let (header, claims) = match (parts.next(), parts.next(), parts.next()) {
+			(Some(header), Some(claims), None) => (header, claims),
+			_ => {
+				eprintln!("Error! Bad JWT format");
+				return Err(());
+			}
+		};
+		let header = serde_json::from_slice(&decode_b64url(header)?).map_err(|err| {
+			eprintln!("Error! Bad JWT header: {:?}", err);
+		})?;
+		let claims = serde_json::from_slice(&decode_b64url(claims)?).map_err(|err| {
+			eprintln!("Error! Bad JWT claims: {:?}", err);
+		})?;
+		Ok(JwtParts {
+			header,
+			claims,
+			signed_data,
+			sign: decode_b64url(&token[sign_start + 1..])?,
+		})
+	}
+
+	/// Check exp (required) and nbf claims (in seconds)
+	pub fn check_time(&self) -> bool {
+		let now = std::time::SystemTime::now()
+			.duration_since(std::time::UNIX_EPOCH)
+			.unwrap_or(std::time::Duration::ZERO)
+			.as_secs();
+		let leeway = 1;
+		match self.claims["exp"].as_u64() {
+			Some(exp) if now > exp.saturating_add(leeway) => {
+				eprintln!("Error! JWT is expired");
+				return false;
+			},
+			Some(_) => (),
+			None => {
+				eprintln!("Error! JWT hasn't claim exp");
+				return false;
+			}
+		}
+		if let Some(nbf) = self.claims["nbf"].as_u64() {
+			if now.saturating_add(leeway) < nbf {
+				eprintln!("Error! JWT isn't valid yet");
+				return false;
+			}
+		}
+		true
+	}
+
+	/// Check iss and aud claims, if expected values are specified.
+	///
+	/// aud may be string or array of strings.
+	pub fn check_issuer_and_audience(&self, issuer: Option<&str>, audience: Option<&str>) -> bool {
+		if let Some(issuer) = issuer {
+			if self.claims["iss"].as_str() != Some(issuer) {
+				eprintln!("Error! Unexpected issuer of JWT: {}", self.claims["iss"]);
+				return false;
+			}
+		}
+		if let Some(audience) = audience {
+			let matched = match self.claims["aud"] {
+				Value::String(ref aud) => aud == audience,
+				Value::Array(ref auds) => auds.iter().any(|aud| aud.as_str() == Some(audience)),
+				_ => false,
+			};
+			if !matched {
+				eprintln!("Error! Unexpected audience of JWT: {}", self.claims["aud"]);
+				return false;
+			}
+		}
+		true
+	}
+}
+
+/// Digest for JWS algorithm
+pub fn get_jws_digest(alg: &str) -> Option<MessageDigest> {
+	match alg {
+		"RS256" | "PS256" | "ES256" => Some(MessageDigest::sha256()),
+		"RS384" | "PS384" | "ES384" => Some(MessageDigest::sha384()),
+		"RS512" | "PS512" | "ES512" => Some(MessageDigest::sha512()),
+		_ => None,
+	}
+}
+
+/// Check, that JWS algorithm is suitable for type (and curve) of key
+pub fn is_jws_alg_for_key(alg: &str, key: &PKey<Public>) -> bool {
+	let curve = || key.ec_key().ok().and_then(|ec_key| ec_key.group().curve_name());
+	match alg {
+		"RS256" | "RS384" | "RS512" | "PS256" | "PS384" | "PS512" => key.id() == Id::RSA,
+		"ES256" => curve() == Some(Nid::X9_62_PRIME256V1),
+		"ES384" => curve() == Some(Nid::SECP384R1),
+		"ES512" => curve() == Some(Nid::SECP521R1),
+		"EdDSA" => key.id() == Id::ED25519 || key.id() == Id::ED448,
+		_ => false,
+	}
+}
+
+/// Verify JWS sign (RS*, PS*, ES*, EdDSA).
+///
+/// Sign is rejected, if algorithm isn't suitable for key.
+pub fn verify_jws_sign(alg: &str, key: &PKey<Public>, data: &[u8], sign: &[u8]) -> bool {
+	if !is_jws_alg_for_key(alg, key) {
+		eprintln!("Error! JWS algorithm {} doesn't match key", alg);
+		return false;
+	}
+	let result = match alg {
+		"EdDSA" => Verifier::new_without_digest(key)
+			.and_then(|mut verifier| verifier.verify_oneshot(sign, data)),
+		_ => {
+			let digest = match get_jws_digest(alg) {
+				Some(digest) => digest,
+				None => {
+					eprintln!("Error! Unsupported JWS algorithm: {}", alg);
+					return false;
+				}
+			};
+			let sign = if alg.starts_with("ES") {
+				match ecdsa_sign_to_der(sign) {
+					Ok(sign) => sign,
+					Err(_) => return false,
+				}
+			} else {
+				sign.to_vec()
+			};
+			Verifier::new(digest, key).and_then(|mut verifier| {
+				if alg.starts_with("PS") {
+					verifier.set_rsa_padding(Padding::PKCS1_PSS)?;
+					verifier.set_rsa_pss_saltlen(RsaPssSaltlen::DIGEST_LENGTH)?;
+					verifier.set_rsa_mgf1_md(digest)?;
+				}
+				verifier.update(data)?;
+				verifier.verify(&sign)
+			})
+		}
+	};
+	match result {
+		Ok(result) => result,
+		Err(err) => {
+			eprintln!("Error! Can't verify JWS sign: {}", err);
+			false
+		}
+	}
+}
+
+/// JWS ECDSA sign is r||s, openssl needs DER.
+fn ecdsa_sign_to_der(sign: &[u8]) -> Result<Vec<u8>, ()> {
+	if sign.is_empty() || !sign.len().is_multiple_of(2) {
+		eprintln!("Error! Bad ECDSA sign length");
+		return Err(());
+	}
+	let (r, s) = sign.split_at(sign.len() / 2);
+	BigNum::from_slice(r)
+		.and_then(|r| Ok((r, BigNum::from_slice(s)?)))
+		.and_then(|(r, s)| EcdsaSig::from_private_components(r, s))
+		.and_then(|ecdsa_sign| ecdsa_sign.to_der())
+		.map_err(|err| {
+			eprintln!("Error! Bad ECDSA sign: {:?}", err);
+		})
+}
+
+/// Decode base64url without padding
+pub fn decode_b64url(data: &str) -> Result<Vec<u8>, ()> {
+	base64::decode_config(data, base64::URL_SAFE_NO_PAD).map_err(|err| {
+		eprintln!("Error! Can't decode base64url: {:?}", err);
+	})
+}
+
+fn decode_b64url_field(field: &Option<String>, name: &str) -> Result<Vec<u8>, ()> {
+	match field {
+		Some(field) => decode_b64url(field),
+		None => {
+			eprintln!("Error! Missing field \"{}\" in JWK", name);
+			Err(())
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use openssl::sign::Signer;
+
+	fn encode_b64url(data: &[u8]) -> String {
+		base64::encode_config(data, base64::URL_SAFE_NO_PAD)
+	}
+
+	#[test]
+	fn rsa_jwk_verifies_jwt() {
+		let rsa = Rsa::generate(2048).unwrap();
+		let jwks = JwkSet::new_from_str(&serde_json::json!({
+			"keys": [{
+				"kty": "RSA",
+				"kid": "key-1",
+				"n": encode_b64url(&rsa.n().to_vec()),
+				"e": encode_b64url(&rsa.e().to_vec()),
+			}, {
+				"kty": "RSA",
+				"use": "enc",
+				"n": encode_b64url(&rsa.n().to_vec()),
+				"e": encode_b64url(&rsa.e().to_vec()),
+			}]
+		}).to_string()).unwrap();
+		let keys = jwks.get_public_keys();
+		assert_eq!(keys.len(), 1);
+		assert_eq!(keys[0].0.as_deref(), Some("key-1"));
+
+		let private_key = PKey::from_rsa(rsa).unwrap();
+		let signed_data = format!(
+			"{}.{}",
+			encode_b64url(br#"{"alg":"RS256","kid":"key-1"}"#),
+			encode_b64url(br#"{"sub":"user","exp":4102444800,"iss":"https://issuer","aud":["api"]}"#)
+		);
+		let mut signer = Signer::new(MessageDigest::sha256(), &private_key).unwrap();
+		signer.update(signed_data.as_bytes()).unwrap();
+		let token = format!("{}.{}", signed_data, encode_b64url(&signer.sign_to_vec().unwrap()));
+		let parts = JwtParts::new_from_str(&token).unwrap();
+		assert_eq!(parts.header.kid.as_deref(), Some("key-1"));
+		assert_eq!(parts.claims["sub"], "user");
+		assert!(parts.check_time());
+		assert!(parts.check_issuer_and_audience(Some("https://issuer"), Some("api")));
+		assert!(!parts.check_issuer_and_audience(Some("https://other"), None));
+		assert!(!parts.check_issuer_and_audience(None, Some("other-api")));
+		assert!(verify_jws_sign("RS256", &keys[0].1, parts.signed_data.as_bytes(), &parts.sign));
+		assert!(!verify_jws_sign("RS256", &keys[0].1, b"other data", &parts.sign));
+		assert!(!verify_jws_sign("ES256", &keys[0].1, parts.signed_data.as_bytes(), &parts.sign));
+		let parts = JwtParts {
+			claims: serde_json::json!({"sub": "user"}),
+			..parts
+		};
+		assert!(!parts.check_time());
+		let parts = JwtParts {
+			claims: serde_json::json!({"sub": "user", "exp": u64::MAX, "nbf": u64::MAX}),
+			..parts
+		};
+		assert!(!parts.check_time());
+	}
+
+	#[test]
+	fn ec_jwk_to_public_key() {
+		let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1).unwrap();
+		let ec_key = EcKey::generate(&group).unwrap();
+		let mut ctx = openssl::bn::BigNumContext::new().unwrap();
+		let mut x = BigNum::new().unwrap();
+		let mut y = BigNum::new().unwrap();
+		ec_key.public_key()
+			.affine_coordinates(&group, &mut x, &mut y, &mut ctx)
+			.unwrap();
+		let jwk = Jwk {
+			kty: "EC".to_string(),
+			crv: Some("P-256".to_string()),
+			x: Some(encode_b64url(&x.to_vec())),
+			y: Some(encode_b64url(&y.to_vec())),
+			..Jwk::default()
+		};
+		let public_key = jwk.to_public_key().unwrap();
+		assert!(public_key.public_eq(&PKey::from_ec_key(ec_key).unwrap()));
+	}
+}
@@ -0,0 +1,638 @@
+use crate::{
+	Verifier,
+	MessageDigest,
+	PKey,
+	Public,
+	Handle,
+	ras_auth_client::{login_to_ras_auth, get_public_keys_from_ras_auth},
+	ras_jwks::{JwkSet, JwtParts, verify_jws_sign},
+};
+use serde_json::Value;
+use std::{
+	collections::HashMap,
+	sync::{Arc, Mutex, RwLock},
+	time::{Duration, Instant},
+};
+use tokio::task::JoinHandle;
+use reqwest::Client;
+
+/// Key id for keys, which ras_auth returns without id.
+pub const DEFAULT_KEY_ID: &str = "default";
+
+/// Timeout of loading keys from ras_auth or JWKS url (by default)
+pub const FETCH_TIMEOUT: Duration = Duration::from_secs(5);
+
+struct StoredKey {
+	key: PKey<Public>,
+	last_seen: Instant,
+	/// Key is added by add_key, it isn't removed by refresh
+	manual: bool,
+	/// Algorithm of key (alg of JWK), JWT with other alg is rejected
+	alg: Option<String>,
+}
+
+/// Source for loading keys.
+pub enum KeySource {
+	/// ras_auth with credentials
+	RasAuth {
+		login: String,
+		password: String,
+		ras_auth_uri: String,
+	},
+	/// JWKS by url
+	JwksUrl(String),
+	/// JWKS in local file
+	JwksFile(String),
+}
+
+/// Store of public keys for token, identified by key id.
+///
+/// Keys are loaded from ras_auth (see from_ras_auth), from JWKS
+/// (see from_jwks_url and from_jwks_file) or added manually.
+/// Refresh in background is started by spawn_refresh.
+/// If token has unknown key id (or store is empty), keys are refetched
+/// (not more often, than min refetch interval) before rejecting.
+///
+/// Key, which is missing in responses of source, is removed after key retention,
+/// keys added by add_key are kept.
+///
+/// JWT must have claim exp, iss and aud are checked, if set_issuer and set_audience are used.
+pub struct KeyStore {
+	keys: RwLock<HashMap<String, StoredKey>>,
+	current_key_id: RwLock<Option<String>>,
+	source: Option<KeySource>,
+	digest: MessageDigest,
+	key_retention: Duration,
+	min_refetch_interval: Duration,
+	last_fetch: Mutex<Option<Instant>>,
+	client: Client,
+	issuer: Option<String>,
+	audience: Option<String>,
+}
+
+impl KeyStore {
+	//constructors:
+	/// Create empty store, keys must be added by add_key.
+	pub fn new() -> KeyStore {
+		KeyStore {
+			keys: RwLock::new(HashMap::new()),
+			current_key_id: RwLock::new(None),
+			source: None,
+			digest: MessageDigest::sha256(),
+			key_retention: Duration::from_secs(300),
+			min_refetch_interval: Duration::from_secs(10),
+			last_fetch: Mutex::new(None),
+			client: build_client(FETCH_TIMEOUT),
+			issuer: None,
+			audience: None,
+		}
+	}
+
+	/// Create store, which loads keys from ras_auth.
+	///
+	/// Keys are not loaded here, call refresh or spawn_refresh.
+	pub fn from_ras_auth(
+		login: String,
+		password: String,
+		ras_auth_uri: String,
+	) -> KeyStore {
+		KeyStore {
+			source: Some(KeySource::RasAuth {login, password, ras_auth_uri}),
+			..KeyStore::new()
+		}
+	}
+
+	/// Create store, which loads keys from JWKS by url.
+	///
+	/// Keys are not loaded here, call refresh or spawn_refresh.
+	pub fn from_jwks_url(url: String) -> KeyStore {
+		KeyStore {
+			source: Some(KeySource::JwksUrl(url)),
+			..KeyStore::new()
+		}
+	}
+
+	/// Create store, which loads keys from JWKS in local file.
+	///
+	/// Keys are not loaded here, call refresh or spawn_refresh.
+	pub fn from_jwks_file(path: String) -> KeyStore {
+		KeyStore {
+			source: Some(KeySource::JwksFile(path)),
+			..KeyStore::new()
+		}
+	}
+
+	/// Specify digest for verification (sha256 by default)
+	pub fn set_digest(mut self, digest: MessageDigest) -> Self {
+		self.digest = digest;
+		self
+	}
+
+	/// Specify time, while key missing in ras_auth is still valid (5 min by default)
+	pub fn set_key_retention(mut self, key_retention: Duration) -> Self {
+		self.key_retention = key_retention;
+		self
+	}
+
+	/// Specify min time between refetching keys (10 sec by default)
+	pub fn set_min_refetch_interval(mut self, interval: Duration) -> Self {
+		self.min_refetch_interval = interval;
+		self
+	}
+
+	/// Specify timeout of loading keys (FETCH_TIMEOUT by default)
+	pub fn set_fetch_timeout(mut self, timeout: Duration) -> Self {
+		self.client = build_client(timeout);
+		self
+	}
+
+	/// Specify expected issuer of JWT (claim iss)
+	pub fn set_issuer(mut self, issuer: &str) -> Self {
+		self.issuer = Some(issuer.to_string());
+		self
+	}
+
+	/// Specify expected audience of JWT (claim aud), usually id of this service
+	pub fn set_audience(mut self, audience: &str) -> Self {
+		self.audience = Some(audience.to_string());
+		self
+	}
+
+	//interface:
+	/// Add key and make it current.
+	pub fn add_key(&self, key_id: &str, key: PKey<Public>) {
+		self.keys
+			.write()
+			.unwrap_or_else(|err| err.into_inner())
+			.insert(key_id.to_string(), StoredKey {
+				key,
+				last_seen: Instant::now(),
+				manual: true,
+				alg: None,
+			});
+		*self.current_key_id
+			.write()
+			.unwrap_or_else(|err| err.into_inner()) = Some(key_id.to_string());
+	}
+
+	/// Remove key
+	pub fn remove_key(&self, key_id: &str) {
+		self.keys
+			.write()
+			.unwrap_or_else(|err| err.into_inner())
+			.remove(key_id);
+		let mut current_key_id = self.current_key_id
+			.write()
+			.unwrap_or_else(|err| err.into_inner());
+		if current_key_id.as_deref() == Some(key_id) {
+			*current_key_id = None;
+		}
+	}
+
+	/// Ids of known keys
+	pub fn key_ids(&self) -> Vec<String> {
+		self.keys
+			.read()
+			.unwrap_or_else(|err| err.into_inner())
+			.keys()
+			.cloned()
+			.collect()
+	}
+
+	/// Id of last added key
+	pub fn current_key_id(&self) -> Option<String> {
+		self.current_key_id
+			.read()
+			.unwrap_or_else(|err| err.into_inner())
+			.clone()
+	}
+
+	/// Load keys from source.
+	pub async fn refresh(&self) -> Result<(), ()> {
+		*self.last_fetch
+			.lock()
+			.unwrap_or_else(|err| err.into_inner()) = Some(Instant::now());
+		self.fetch().await
+	}
+
+	/// Start refreshing keys in background.
+	pub fn spawn_refresh(
+		self: &Arc<Self>,
+		runtime: &Handle,
+		interval: Duration,
+	) -> JoinHandle<()> {
+		let key_store = self.clone();
+		runtime.spawn(async move {
+			let mut timer = tokio::time::interval(interval);
+			loop {
+				timer.tick().await;
+				if key_store.refresh().await.is_err() {
+					eprintln!("Error! Can't refresh keys for token");
+				}
+			}
+		})
+	}
+
+	/// Verify sign of json.
+	///
+	/// If key_id is None, all known keys are checked (current key is first).
+	/// If key_id is unknown, keys are refetched before rejecting.
+	pub fn verify(&self, json: &str, sign: &str, key_id: Option<&str>) -> bool {
+		let sign = match base64::decode(sign) {
+			Ok(sign) => sign,
+			Err(err) => {
+				eprintln!("Error! Can't decode sign of token: {:?}", err);
+				return false;
+			}
+		};
+		match self.verify_by_known_keys(json.as_bytes(), &sign, key_id) {
+			Some(result) => result,
+			None => {
+				if !self.refetch_blocking() {
+					return false;
+				}
+				self.verify_by_known_keys(json.as_bytes(), &sign, key_id)
+					.unwrap_or(false)
+			}
+		}
+	}
+
+	/// Verify JWT (compact JWS) and return its claims.
+	///
+	/// Key is selected by kid from header of JWT,
+	/// if kid is missing, all known keys are checked.
+	/// If kid is unknown, keys are refetched before rejecting.
+	/// Header alg must be suitable for key (and equal to alg of JWK, if it is specified).
+	/// Claims exp (required), nbf, iss and aud are checked.
+	pub fn verify_jwt(&self, token: &str) -> Result<Value, ()> {
+		let parts = JwtParts::new_from_str(token)?;
+		let verified = match self.verify_jwt_by_known_keys(&parts) {
+			Some(result) => result,
+			None => {
+				self.refetch_blocking()
+					&& self.verify_jwt_by_known_keys(&parts).unwrap_or(false)
+			}
+		};
+		if !verified {
+			eprintln!("Error! Bad sign of JWT");
+			return Err(());
+		}
+		if !parts.check_time()
+			|| !parts.check_issuer_and_audience(self.issuer.as_deref(), self.audience.as_deref()) {
+			return Err(());
+		}
+		Ok(parts.claims)
+	}
+
+	//inner functions:
+	/// Return None, if kid of JWT is unknown (or store is empty).
+	fn verify_jwt_by_known_keys(&self, parts: &JwtParts) -> Option<bool> {
+		let keys = self.keys.read().unwrap_or_else(|err| err.into_inner());
+		let data = parts.signed_data.as_bytes();
+		let alg = parts.header.alg.as_str();
+		let verify = |stored: &StoredKey| {
+			stored.alg.as_deref().is_none_or(|key_alg| key_alg == alg)
+				&& verify_jws_sign(alg, &stored.key, data, &parts.sign)
+		};
+		if let Some(ref key_id) = parts.header.kid {
+			return keys.get(key_id).map(verify);
+		}
+		if keys.is_empty() {
+			return None;
+		}
+		Some(keys.values().any(verify))
+	}
+
+	/// Return None, if key_id is unknown (or store is empty).
+	fn verify_by_known_keys(
+		&self,
+		data: &[u8],
+		sign: &[u8],
+		key_id: Option<&str>,
+	) -> Option<bool> {
+		let keys = self.keys.read().unwrap_or_else(|err| err.into_inner());
+		if let Some(key_id) = key_id {
+			return keys.get(key_id)
+				.map(|stored| self.verify_by_key(&stored.key, data, sign));
+		}
+		if keys.is_empty() {
+			return None;
+		}
+		let current_key_id = self.current_key_id();
+		let mut ordered_keys: Vec<(&String, &StoredKey)> = keys.iter().collect();
+		ordered_keys.sort_by_key(|(id, _)| Some(*id) != current_key_id.as_ref());
+		Some(ordered_keys.iter().any(|(_, stored)| self.verify_by_key(&stored.key, data, sign)))
+	}
+
+	fn verify_by_key(&self, key: &PKey<Public>, data: &[u8], sign: &[u8]) -> bool {
+		let mut verifier = match Verifier::new(self.digest, key) {
+			Ok(verifier) => verifier,
+			Err(err) => {
+				eprintln!("Error! Can't create verifier for token: {}", err);
+				return false;
+			}
+		};
+		if let Err(err) = verifier.update(data) {
+			eprintln!("Error! Can't update data to verifier: {}", err);
+			return false;
+		}
+		verifier.verify(sign).unwrap_or(false)
+	}
+
+	/// Refetch keys, if min refetch interval is passed.
+	fn refetch_blocking(&self) -> bool {
+		if self.source.is_none() {
+			return false;
+		}
+		{
+			let mut last_fetch = self.last_fetch
+				.lock()
+				.unwrap_or_else(|err| err.into_inner());
+			if let Some(last_fetch) = *last_fetch {
+				if last_fetch.elapsed() < self.min_refetch_interval {
+					return false;
+				}
+			}
+			*last_fetch = Some(Instant::now());
+		}
+		let result = match Handle::try_current() {
+			Ok(handle) => {
+				if handle.runtime_flavor() != tokio::runtime::RuntimeFlavor::MultiThread {
+					eprintln!("Error! Can't refetch keys in current thread runtime");
+					return false;
+				}
+				tokio::task::block_in_place(|| handle.block_on(self.fetch()))
+			},
+			Err(_) => {
+				match tokio::runtime::Builder::new_current_thread().enable_all().build() {
+					Ok(runtime) => runtime.block_on(self.fetch()),
+					Err(err) => {
+						eprintln!("Error! Can't build runtime for refetch keys: {:?}", err);
+						return false;
+					}
+				}
+			}
+		};
+		result.is_ok()
+	}
+
+	async fn fetch(&self) -> Result<(), ()> {
+		let fetched_keys: Vec<(Option<String>, Option<String>, PKey<Public>)> = match self.source {
+			Some(KeySource::RasAuth {ref login, ref password, ref ras_auth_uri}) => {
+				let tokens = login_to_ras_auth(
+					&self.client,
+					login,
+					password,
+					ras_auth_uri
+				).await?;
+				let access_token = match tokens["access_token"].as_str() {
+					Some(access_token) => access_token,
+					None => {
+						eprintln!("Error! Can't find access token in response");
+						return Err(());
+					}
+				};
+				get_public_keys_from_ras_auth(
+					&self.client,
+					access_token,
+					ras_auth_uri
+				).await?
+					.into_iter()
+					.map(|(key_id, key)| (key_id, None, key))
+					.collect()
+			},
+			Some(KeySource::JwksUrl(ref url)) =>
+				JwkSet::new_from_url(&self.client, url).await?.get_public_keys_with_alg(),
+			Some(KeySource::JwksFile(ref path)) =>
+				JwkSet::new_from_file(path).await?.get_public_keys_with_alg(),
+			None => return Ok(()),
+		};
+		let now = Instant::now();
+		let mut keys = self.keys.write().unwrap_or_else(|err| err.into_inner());
+		let mut current_key_id = None;
+		for (index, (key_id, alg, key)) in fetched_keys.into_iter().enumerate() {
+			let key_id = key_id.unwrap_or_else(|| match index {
+				0 => DEFAULT_KEY_ID.to_string(),
+				_ => format!("{}-{}", DEFAULT_KEY_ID, index),
+			});
+			if current_key_id.is_none() {
+				current_key_id = Some(key_id.clone());
+			}
+			keys.insert(key_id, StoredKey {key, last_seen: now, manual: false, alg});
+		}
+		let key_retention = self.key_retention;
+		keys.retain(|_, stored| {
+			stored.manual || now.duration_since(stored.last_seen) <= key_retention
+		});
+		if current_key_id.is_some() {
+			*self.current_key_id
+				.write()
+				.unwrap_or_else(|err| err.into_inner()) = current_key_id;
+		}
+		Ok(())
+	}
+}
+
+impl Default for KeyStore {
+	fn default() -> Self {
+		KeyStore::new()
+	}
+}
+
+fn build_client(timeout: Duration) -> Client {
+	Client::builder()
+		.timeout(timeout)
+		.build()
+		.unwrap_or_else(|err| {
+			eprintln!("Error! Can't build http client for keys: {:?}", err);
+			Client::new()
+		})
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use openssl::{rsa::Rsa, pkey::Private, sign::Signer};
+
+	fn generate_key() -> (PKey<Private>, PKey<Public>) {
+		let private_key = PKey::from_rsa(Rsa::generate(2048).unwrap()).unwrap();
+		let public_key = PKey::public_key_from_pem(
+			&private_key.public_key_to_pem().unwrap()
+		).unwrap();
+		(private_key, public_key)
+	}
+
+	fn sign(key: &PKey<Private>, json: &str) -> String {
+		let mut signer = Signer::new(MessageDigest::sha256(), key).unwrap();
+		signer.update(json.as_bytes()).unwrap();
+		base64::encode(signer.sign_to_vec().unwrap())
+	}
+
+	fn encode_b64url(data: &[u8]) -> String {
+		base64::encode_config(data, base64::URL_SAFE_NO_PAD)
+	}
+
+	fn write_jwks(path: &std::path::Path, keys: &[(&str, &PKey<Private>)]) {
+		let keys: Vec<Value> = keys
+			.iter()
+			.map(|(key_id, key)| {
+				let rsa = key.rsa().unwrap();
+				serde_json::json!({
+					"kty": "RSA",
+					"kid": key_id,
+					"alg": "RS256",
+					"n": encode_b64url(&rsa.n().to_vec()),
+					"e": encode_b64url(&rsa.e().to_vec()),
+				})
+			})
+			.collect();
+		std::fs::write(path, serde_json::json!({"keys": keys}).to_string()).unwrap();
+	}
+
+	fn exp() -> u64 {
+		std::time::SystemTime::now()
+			.duration_since(std::time::UNIX_EPOCH)
+			.unwrap()
+			.as_secs() + 60
+	}
+
+	fn sign_jwt(key: &PKey<Private>, header: Value, claims: Value) -> String {
+		let digest = match header["alg"].as_str() {
+			Some("RS384") => MessageDigest::sha384(),
+			_ => MessageDigest::sha256(),
+		};
+		let signed_data = format!(
+			"{}.{}",
+			encode_b64url(header.to_string().as_bytes()),
+			encode_b64url(claims.to_string().as_bytes())
+		);
+		let mut signer = Signer::new(digest, key).unwrap();
+		signer.update(signed_data.as_bytes()).unwrap();
+		format!("{}.{}", signed_data, encode_b64url(&signer.sign_to_vec().unwrap()))
+	}
+
+	fn jwt(key: &PKey<Private>, key_id: &str) -> String {
+		sign_jwt(
+			key,
+			serde_json::json!({"alg": "RS256", "kid": key_id}),
+			serde_json::json!({"sub": "user", "exp": exp()})
+		)
+	}
+
+	fn temp_path(name: &str) -> std::path::PathBuf {
+		std::env::temp_dir().join(format!("ras_key_store_{}_{}.json", name, std::process::id()))
+	}
+
+	#[test]
+	fn verify_with_rotated_keys() {
+		let (old_private, old_public) = generate_key();
+		let (new_private, new_public) = generate_key();
+		let key_store = KeyStore::new();
+		key_store.add_key("old", old_public);
+		key_store.add_key("new", new_public);
+		assert_eq!(key_store.current_key_id(), Some("new".to_string()));
+		let json = "some token";
+		assert!(key_store.verify(json, &sign(&old_private, json), Some("old")));
+		assert!(key_store.verify(json, &sign(&old_private, json), None));
+		assert!(key_store.verify(json, &sign(&new_private, json), None));
+		assert!(!key_store.verify(json, &sign(&old_private, json), Some("new")));
+		assert!(!key_store.verify(json, &sign(&new_private, json), Some("unknown")));
+		key_store.remove_key("old");
+		assert!(!key_store.verify(json, &sign(&old_private, json), None));
+	}
+
+	#[test]
+	fn refresh_keeps_manual_keys() {
+		let (first_private, _) = generate_key();
+		let (second_private, _) = generate_key();
+		let (_, manual_public) = generate_key();
+		let path = temp_path("refresh");
+		write_jwks(&path, &[("first", &first_private)]);
+		let key_store = KeyStore::from_jwks_file(path.to_string_lossy().to_string())
+			.set_key_retention(Duration::ZERO);
+		key_store.add_key("manual", manual_public);
+		let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+		runtime.block_on(key_store.refresh()).unwrap();
+		let mut key_ids = key_store.key_ids();
+		key_ids.sort();
+		assert_eq!(key_ids, ["first", "manual"]);
+		assert_eq!(key_store.current_key_id(), Some("first".to_string()));
+
+		std::thread::sleep(Duration::from_millis(5));
+		write_jwks(&path, &[("second", &second_private)]);
+		runtime.block_on(key_store.refresh()).unwrap();
+		let mut key_ids = key_store.key_ids();
+		key_ids.sort();
+		assert_eq!(key_ids, ["manual", "second"]);
+		std::fs::remove_file(&path).unwrap();
+	}
+
+	#[test]
+	fn refetch_only_for_unknown_key_id() {
+		let (old_private, _) = generate_key();
+		let (new_private, _) = generate_key();
+		let path = temp_path("refetch");
+		write_jwks(&path, &[("old", &old_private)]);
+		let key_store = KeyStore::from_jwks_file(path.to_string_lossy().to_string())
+			.set_min_refetch_interval(Duration::ZERO);
+		assert!(key_store.verify_jwt(&jwt(&old_private, "old")).is_ok());
+
+		write_jwks(&path, &[("old", &old_private), ("new", &new_private)]);
+		// sign without key id doesn't cause refetch
+		let json = "some token";
+		assert!(!key_store.verify(json, &sign(&new_private, json), None));
+		assert_eq!(key_store.key_ids(), ["old"]);
+		// unknown kid causes refetch
+		assert!(key_store.verify_jwt(&jwt(&new_private, "new")).is_ok());
+		assert!(key_store.verify(json, &sign(&new_private, json), None));
+
+		// refetch isn't repeated before min refetch interval
+		let key_store = KeyStore::from_jwks_file(path.to_string_lossy().to_string())
+			.set_min_refetch_interval(Duration::from_secs(60));
+		std::fs::remove_file(&path).unwrap();
+		assert!(key_store.verify_jwt(&jwt(&old_private, "unknown")).is_err());
+		write_jwks(&path, &[("old", &old_private)]);
+		assert!(key_store.verify_jwt(&jwt(&old_private, "old")).is_err());
+		std::fs::remove_file(&path).unwrap();
+	}
+
+	#[test]
+	fn jwt_claims_and_alg_are_checked() {
+		let (private_key, _) = generate_key();
+		let path = temp_path("claims");
+		write_jwks(&path, &[("key", &private_key)]);
+		let key_store = KeyStore::from_jwks_file(path.to_string_lossy().to_string())
+			.set_issuer("https://issuer")
+			.set_audience("orders");
+		let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+		runtime.block_on(key_store.refresh()).unwrap();
+		std::fs::remove_file(&path).unwrap();
+		let header = serde_json::json!({"alg": "RS256", "kid": "key"});
+		let claims = |iss: &str, aud: Value| serde_json::json!({
+			"sub": "user",
+			"exp": exp(),
+			"iss": iss,
+			"aud": aud,
+		});
+		let jwt = sign_jwt(&private_key, header.clone(), claims("https://issuer", "orders".into()));
+		assert_eq!(key_store.verify_jwt(&jwt).unwrap()["sub"], "user");
+		let jwt = sign_jwt(&private_key, header.clone(), claims("https://issuer", serde_json::json!(["users", "orders"])));
+		assert!(key_store.verify_jwt(&jwt).is_ok());
+		let jwt = sign_jwt(&private_key, header.clone(), claims("https://other", "orders".into()));
+		assert!(key_store.verify_jwt(&jwt).is_err());
+		let jwt = sign_jwt(&private_key, header.clone(), claims("https://issuer", "users".into()));
+		assert!(key_store.verify_jwt(&jwt).is_err());
+		let jwt = sign_jwt(&private_key, header, serde_json::json!({
+			"iss": "https://issuer",
+			"aud": "orders",
+		}));
+		assert!(key_store.verify_jwt(&jwt).is_err());
+		// alg of JWT differs from alg of JWK
+		let jwt = sign_jwt(
+			&private_key,
+			serde_json::json!({"alg": "RS384", "kid": "key"}),
+			claims("https://issuer", "orders".into())
+		);
+		assert!(key_store.verify_jwt(&jwt).is_err());
+	}
+}
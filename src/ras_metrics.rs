@@ -0,0 +1,470 @@
+use crate::{
+	Arc,
+	HashMap,
+	Mutex,
+};
+use std::{
+	fmt::Write,
+	sync::OnceLock,
+	time::Instant,
+};
+
+/// Buckets of histogram of request duration (seconds)
+pub const DEFAULT_BUCKETS: [f64; 11] =
+	[0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0];
+
+/// Content type of Prometheus text exposition format
+pub const CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+/// Values of metric by values of labels
+type Values<V> = Mutex<HashMap<Vec<String>, V>>;
+
+fn get_label_values(labels: &[&str]) -> Vec<String> {
+	labels.iter().map(|label| label.to_string()).collect()
+}
+
+/// Counter, which is only increased
+pub struct Counter {
+	name: String,
+	help: String,
+	label_names: Vec<String>,
+	values: Values<f64>,
+}
+
+impl Counter {
+	/// Increase by 1, values of labels are in order of registration
+	pub fn inc(&self, labels: &[&str]) {
+		self.add(labels, 1.0);
+	}
+
+	pub fn add(&self, labels: &[&str], value: f64) {
+		let mut values = self.values.lock().unwrap_or_else(|err| err.into_inner());
+		*values.entry(get_label_values(labels)).or_insert(0.0) += value;
+	}
+
+	pub fn get(&self, labels: &[&str]) -> f64 {
+		let values = self.values.lock().unwrap_or_else(|err| err.into_inner());
+		values.get(&get_label_values(labels)).copied().unwrap_or(0.0)
+	}
+}
+
+/// Gauge, which can be set, increased and decreased
+pub struct Gauge {
+	name: String,
+	help: String,
+	label_names: Vec<String>,
+	values: Values<f64>,
+}
+
+impl Gauge {
+	pub fn set(&self, labels: &[&str], value: f64) {
+		let mut values = self.values.lock().unwrap_or_else(|err| err.into_inner());
+		values.insert(get_label_values(labels), value);
+	}
+
+	pub fn add(&self, labels: &[&str], value: f64) {
+		let mut values = self.values.lock().unwrap_or_else(|err| err.into_inner());
+		*values.entry(get_label_values(labels)).or_insert(0.0) += value;
+	}
+
+	pub fn inc(&self, labels: &[&str]) {
+		self.add(labels, 1.0);
+	}
+
+	pub fn dec(&self, labels: &[&str]) {
+		self.add(labels, -1.0);
+	}
+
+	pub fn get(&self, labels: &[&str]) -> f64 {
+		let values = self.values.lock().unwrap_or_else(|err| err.into_inner());
+		values.get(&get_label_values(labels)).copied().unwrap_or(0.0)
+	}
+}
+
+#[derive(Clone, Default)]
+struct HistogramValue {
+	/// Counts of buckets (not cumulative)
+	buckets: Vec<u64>,
+	sum: f64,
+	count: u64,
+}
+
+/// Histogram with fixed buckets
+pub struct Histogram {
+	name: String,
+	help: String,
+	label_names: Vec<String>,
+	buckets: Vec<f64>,
+	values: Values<HistogramValue>,
+}
+
+impl Histogram {
+	pub fn observe(&self, labels: &[&str], value: f64) {
+		let mut values = self.values.lock().unwrap_or_else(|err| err.into_inner());
+		let histogram_value = values
+			.entry(get_label_values(labels))
+			.or_insert_with(|| HistogramValue {
+				buckets: vec![0; self.buckets.len()],
+				..HistogramValue::default()
+			});
+		if let Some(bucket) = self.buckets.iter().position(|bound| value <= *bound) {
+			histogram_value.buckets[bucket] += 1;
+		}
+		histogram_value.sum += value;
+		histogram_value.count += 1;
+	}
+
+	/// Count of observations
+	pub fn get_count(&self, labels: &[&str]) -> u64 {
+		let values = self.values.lock().unwrap_or_else(|err| err.into_inner());
+		values.get(&get_label_values(labels)).map_or(0, |value| value.count)
+	}
+}
+
+#[derive(Clone)]
+enum Metric {
+	Counter(Arc<Counter>),
+	Gauge(Arc<Gauge>),
+	Histogram(Arc<Histogram>),
+}
+
+impl Metric {
+	fn get_name(&self) -> &str {
+		match self {
+			Metric::Counter(counter) => &counter.name,
+			Metric::Gauge(gauge) => &gauge.name,
+			Metric::Histogram(histogram) => &histogram.name,
+		}
+	}
+}
+
+/// Registry of metrics.
+///
+/// Metrics of service and of handlers are in global registry
+/// and are exposed by path of RasServiceBuilder::set_metrics_path.
+///
+/// # Examples
+///
+/// ```ignore
+/// let orders = ras_metrics::registry()
+///     .register_counter("orders_created_total", "Count of created orders", &["kind"]);
+/// orders.inc(&["retail"]);
+/// ```
+#[derive(Default)]
+pub struct Registry {
+	metrics: Mutex<Vec<Metric>>,
+}
+
+impl Registry {
+	//constructor:
+	pub fn new() -> Registry {
+		Registry::default()
+	}
+
+	//interface:
+	/// Register counter (or get registered with the same name)
+	pub fn register_counter(&self, name: &str, help: &str, label_names: &[&str]) -> Arc<Counter> {
+		let counter = Metric::Counter(Arc::new(Counter {
+			name: name.to_string(),
+			help: help.to_string(),
+			label_names: get_label_values(label_names),
+			values: Mutex::new(HashMap::new()),
+		}));
+		match self.register(counter) {
+			Metric::Counter(counter) => counter,
+			_ => unreachable!(),
+		}
+	}
+
+	/// Register gauge (or get registered with the same name)
+	pub fn register_gauge(&self, name: &str, help: &str, label_names: &[&str]) -> Arc<Gauge> {
+		let gauge = Metric::Gauge(Arc::new(Gauge {
+			name: name.to_string(),
+			help: help.to_string(),
+			label_names: get_label_values(label_names),
+			values: Mutex::new(HashMap::new()),
+		}));
+		match self.register(gauge) {
+			Metric::Gauge(gauge) => gauge,
+			_ => unreachable!(),
+		}
+	}
+
+	/// Register histogram (or get registered with the same name).
+	///
+	/// Buckets are upper bounds in ascending order, +Inf is added by itself.
+	pub fn register_histogram(
+		&self,
+		name: &str,
+		help: &str,
+		label_names: &[&str],
+		buckets: &[f64],
+	) -> Arc<Histogram> {
+		let histogram = Metric::Histogram(Arc::new(Histogram {
+			name: name.to_string(),
+			help: help.to_string(),
+			label_names: get_label_values(label_names),
+			buckets: buckets.to_vec(),
+			values: Mutex::new(HashMap::new()),
+		}));
+		match self.register(histogram) {
+			Metric::Histogram(histogram) => histogram,
+			_ => unreachable!(),
+		}
+	}
+
+	/// Metrics in Prometheus text exposition format
+	pub fn render(&self) -> String {
+		let metrics = self.metrics.lock().unwrap_or_else(|err| err.into_inner()).clone();
+		let mut result = String::new();
+		for metric in metrics {
+			match metric {
+				Metric::Counter(counter) => render_values(
+					&mut result, "counter", &counter.name, &counter.help,
+					&counter.label_names, &counter.values,
+				),
+				Metric::Gauge(gauge) => render_values(
+					&mut result, "gauge", &gauge.name, &gauge.help,
+					&gauge.label_names, &gauge.values,
+				),
+				Metric::Histogram(histogram) => render_histogram(&mut result, &histogram),
+			}
+		}
+		result
+	}
+
+	//inner functions:
+	/// Metric with the same name and type is reused,
+	/// metric with the same name and other type isn't exposed
+	fn register(&self, metric: Metric) -> Metric {
+		let mut metrics = self.metrics.lock().unwrap_or_else(|err| err.into_inner());
+		let registered = metrics
+			.iter()
+			.find(|registered| registered.get_name() == metric.get_name());
+		match registered {
+			Some(registered) if std::mem::discriminant(registered) == std::mem::discriminant(&metric) =>
+				registered.clone(),
+			Some(_) => {
+				eprintln!("Error! Metric {} is registered with other type", metric.get_name());
+				metric
+			},
+			None => {
+				metrics.push(metric.clone());
+				metric
+			},
+		}
+	}
+}
+
+fn render_header(result: &mut String, metric_type: &str, name: &str, help: &str) {
+	let help = help.replace('\\', "\\\\").replace('\n', "\\n");
+	let _ = writeln!(result, "# HELP {} {}", name, help);
+	let _ = writeln!(result, "# TYPE {} {}", name, metric_type);
+}
+
+/// Labels as {name="value",...}, extra label is added to end (for le of buckets)
+fn render_labels(label_names: &[String], values: &[String], extra: Option<(&str, &str)>) -> String {
+	let labels: Vec<String> = label_names
+		.iter()
+		.map(String::as_str)
+		.zip(values.iter().map(String::as_str))
+		.chain(extra)
+		.map(|(name, value)| format!(
+			"{}=\"{}\"",
+			name,
+			value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+		))
+		.collect();
+	if labels.is_empty() {
+		String::new()
+	} else {
+		format!("{{{}}}", labels.join(","))
+	}
+}
+
+fn render_values(
+	result: &mut String,
+	metric_type: &str,
+	name: &str,
+	help: &str,
+	label_names: &[String],
+	values: &Values<f64>,
+) {
+	render_header(result, metric_type, name, help);
+	let mut values: Vec<(Vec<String>, f64)> = values
+		.lock()
+		.unwrap_or_else(|err| err.into_inner())
+		.iter()
+		.map(|(labels, value)| (labels.clone(), *value))
+		.collect();
+	values.sort_by(|first, second| first.0.cmp(&second.0));
+	for (labels, value) in values {
+		let _ = writeln!(result, "{}{} {}", name, render_labels(label_names, &labels, None), value);
+	}
+}
+
+fn render_histogram(result: &mut String, histogram: &Histogram) {
+	render_header(result, "histogram", &histogram.name, &histogram.help);
+	let mut values: Vec<(Vec<String>, HistogramValue)> = histogram.values
+		.lock()
+		.unwrap_or_else(|err| err.into_inner())
+		.iter()
+		.map(|(labels, value)| (labels.clone(), value.clone()))
+		.collect();
+	values.sort_by(|first, second| first.0.cmp(&second.0));
+	let name = &histogram.name;
+	let label_names = &histogram.label_names;
+	for (labels, value) in values {
+		let mut cumulative = 0;
+		for (bound, count) in histogram.buckets.iter().zip(value.buckets) {
+			cumulative += count;
+			let le = bound.to_string();
+			let _ = writeln!(result, "{}_bucket{} {}",
+				name, render_labels(label_names, &labels, Some(("le", &le))), cumulative);
+		}
+		let _ = writeln!(result, "{}_bucket{} {}",
+			name, render_labels(label_names, &labels, Some(("le", "+Inf"))), value.count);
+		let _ = writeln!(result, "{}_sum{} {}",
+			name, render_labels(label_names, &labels, None), value.sum);
+		let _ = writeln!(result, "{}_count{} {}",
+			name, render_labels(label_names, &labels, None), value.count);
+	}
+}
+
+/// Global registry
+pub fn registry() -> &'static Registry {
+	static REGISTRY: OnceLock<Registry> = OnceLock::new();
+	REGISTRY.get_or_init(Registry::new)
+}
+
+/// Metrics of service (in global registry)
+pub(crate) struct ServiceMetrics {
+	requests: Arc<Counter>,
+	request_errors: Arc<Counter>,
+	request_duration: Arc<Histogram>,
+	requests_in_flight: Arc<Gauge>,
+	pub(crate) accept_errors: Arc<Counter>,
+	auth_failures: Arc<Counter>,
+}
+
+pub(crate) fn service_metrics() -> &'static ServiceMetrics {
+	static SERVICE_METRICS: OnceLock<ServiceMetrics> = OnceLock::new();
+	SERVICE_METRICS.get_or_init(|| {
+		let registry = registry();
+		ServiceMetrics {
+			requests: registry.register_counter(
+				"ras_requests_total",
+				"Count of processed requests",
+				&["method", "route", "status"],
+			),
+			request_errors: registry.register_counter(
+				"ras_request_errors_total",
+				"Count of requests with status 4xx and 5xx",
+				&["method", "route", "status"],
+			),
+			request_duration: registry.register_histogram(
+				"ras_request_duration_seconds",
+				"Duration of processing of requests",
+				&["method", "route"],
+				&DEFAULT_BUCKETS,
+			),
+			requests_in_flight: registry.register_gauge(
+				"ras_requests_in_flight",
+				"Count of requests in processing",
+				&["method", "route"],
+			),
+			accept_errors: registry.register_counter(
+				"ras_accept_errors_total",
+				"Count of errors of accepting connections",
+				&[],
+			),
+			auth_failures: registry.register_counter(
+				"ras_auth_failures_total",
+				"Count of failed checks of credentials",
+				&["reason"],
+			),
+		}
+	})
+}
+
+/// Count failed check of credentials (also for own authenticators)
+pub fn auth_failure(reason: &str) {
+	service_metrics().auth_failures.inc(&[reason]);
+}
+
+/// Label of HTTP method, other methods share one label to limit count of series
+fn method_label(method: &str) -> &'static str {
+	match method {
+		"GET" => "GET",
+		"POST" => "POST",
+		"OPTIONS" => "OPTIONS",
+		_ => "OTHER",
+	}
+}
+
+/// Measurement of request, request is in flight until drop
+pub(crate) struct RequestTimer {
+	method: &'static str,
+	route: String,
+	start: Instant,
+}
+
+impl RequestTimer {
+	pub(crate) fn start(method: &str, route: &str) -> RequestTimer {
+		let method = method_label(method);
+		service_metrics().requests_in_flight.inc(&[method, route]);
+		RequestTimer {
+			method,
+			route: route.to_string(),
+			start: Instant::now(),
+		}
+	}
+
+	pub(crate) fn finish(self, status: u16) {
+		let metrics = service_metrics();
+		let status_label = status.to_string();
+		let labels = [self.method, self.route.as_str(), status_label.as_str()];
+		metrics.requests.inc(&labels);
+		if status >= 400 {
+			metrics.request_errors.inc(&labels);
+		}
+		metrics.request_duration.observe(
+			&[self.method, &self.route],
+			self.start.elapsed().as_secs_f64(),
+		);
+	}
+}
+
+impl Drop for RequestTimer {
+	fn drop(&mut self) {
+		service_metrics().requests_in_flight.dec(&[self.method, &self.route]);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn text_exposition_format() {
+		let registry = Registry::new();
+		let counter = registry.register_counter("jobs_total", "Count of jobs", &["kind"]);
+		counter.inc(&["a\"b"]);
+		registry.register_counter("jobs_total", "Count of jobs", &["kind"]).add(&["a\"b"], 2.0);
+		let histogram = registry.register_histogram("job_seconds", "Duration", &[], &[0.1, 1.0]);
+		histogram.observe(&[], 0.5);
+		histogram.observe(&[], 5.0);
+		assert_eq!(registry.render(), concat!(
+			"# HELP jobs_total Count of jobs\n",
+			"# TYPE jobs_total counter\n",
+			"jobs_total{kind=\"a\\\"b\"} 3\n",
+			"# HELP job_seconds Duration\n",
+			"# TYPE job_seconds histogram\n",
+			"job_seconds_bucket{le=\"0.1\"} 0\n",
+			"job_seconds_bucket{le=\"1\"} 1\n",
+			"job_seconds_bucket{le=\"+Inf\"} 2\n",
+			"job_seconds_sum 5.5\n",
+			"job_seconds_count 2\n",
+		));
+	}
+}
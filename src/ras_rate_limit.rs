@@ -0,0 +1,316 @@
+use crate::{
+	Arc,
+	HashMap,
+	HttpStatus,
+	Mutex,
+	RasResponse,
+	RequestContext,
+};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Rate: count of requests (burst) per period
+#[derive(Debug, Clone)]
+pub struct RateLimit {
+	pub requests: u32,
+	pub period: Duration,
+}
+
+impl RateLimit {
+	pub fn new(requests: u32, period: Duration) -> RateLimit {
+		RateLimit {
+			requests,
+			period,
+		}
+	}
+
+	pub fn per_second(requests: u32) -> RateLimit {
+		RateLimit::new(requests, Duration::from_secs(1))
+	}
+
+	pub fn per_minute(requests: u32) -> RateLimit {
+		RateLimit::new(requests, Duration::from_secs(60))
+	}
+}
+
+/// Key of client for rate limiting
+#[derive(Debug, Clone, PartialEq)]
+pub enum RateLimitKey {
+	/// Address of peer
+	PeerAddr,
+	/// Address of header X-Forwarded-For, which is added by outer of trusted proxies
+	/// (with count of trusted proxies n it is n-th address from the right,
+	/// addresses on the left are set by client).
+	///
+	/// Use only behind trusted proxies, address of peer if header is shorter.
+	ForwardedFor(usize),
+	/// User name of authenticated caller (user_name of AccessToken),
+	/// address of peer for anonymous caller.
+	///
+	/// Is checked after authentication, other keys are checked before it.
+	#[cfg(feature = "Authentication")]
+	User,
+}
+
+impl RateLimitKey {
+	pub fn get_key(&self, request: &RequestContext) -> String {
+		let peer_addr = || request.peer_addr
+			.map(|peer_addr| peer_addr.ip().to_string())
+			.unwrap_or_else(|| "unknown".to_string());
+		match self {
+			RateLimitKey::PeerAddr => peer_addr(),
+			RateLimitKey::ForwardedFor(trusted_proxies) => {
+				// proxies may append address to header or add new header
+				let addrs: Vec<&str> = request.headers
+					.iter()
+					.filter(|(name, _)| name.eq_ignore_ascii_case("X-Forwarded-For"))
+					.flat_map(|(_, addrs)| addrs.split(','))
+					.map(str::trim)
+					.collect();
+				addrs.len()
+					.checked_sub((*trusted_proxies).max(1))
+					.map(|index| addrs[index])
+					.filter(|addr| !addr.is_empty())
+					.map(|addr| addr.to_string())
+					.unwrap_or_else(peer_addr)
+			},
+			#[cfg(feature = "Authentication")]
+			RateLimitKey::User => match request.principal {
+				Some(ref principal) => format!("user:{}", principal.user_name),
+				None => peer_addr(),
+			},
+		}
+	}
+
+	/// Key is known only after authentication
+	pub fn is_by_user(&self) -> bool {
+		match self {
+			#[cfg(feature = "Authentication")]
+			RateLimitKey::User => true,
+			_ => false,
+		}
+	}
+}
+
+/// Decision of rate limiter
+#[derive(Debug, Clone, PartialEq)]
+pub struct RateLimitDecision {
+	pub allowed: bool,
+	pub limit: u32,
+	pub remaining: u32,
+	/// Seconds until limit is fully restored
+	pub reset: u64,
+	/// Seconds until next request is allowed (if not allowed)
+	pub retry_after: Option<u64>,
+}
+
+impl RateLimitDecision {
+	/// Headers RateLimit-Limit, RateLimit-Remaining, RateLimit-Reset
+	/// (and Retry-After, if not allowed)
+	pub fn get_headers(&self) -> Vec<(String, String)> {
+		let mut headers = vec![
+			("RateLimit-Limit".to_string(), self.limit.to_string()),
+			("RateLimit-Remaining".to_string(), self.remaining.to_string()),
+			("RateLimit-Reset".to_string(), self.reset.to_string()),
+		];
+		if let Some(retry_after) = self.retry_after {
+			headers.push(("Retry-After".to_string(), retry_after.to_string()));
+		}
+		headers
+	}
+}
+
+/// GCRA (generic cell rate algorithm).
+///
+/// tat is theoretical arrival time in ms, return new tat and decision.
+pub fn gcra(tat: Option<u128>, now: u128, limit: &RateLimit) -> (u128, RateLimitDecision) {
+	let period = limit.period.as_millis();
+	let interval = (period / u128::from(limit.requests.max(1))).max(1);
+	let tat = tat.unwrap_or(now).max(now);
+	let new_tat = tat + interval;
+	let allowed = new_tat - now <= period;
+	let used_until = if allowed { new_tat } else { tat };
+	let remaining = (period.saturating_sub(used_until - now) / interval) as u32;
+	let decision = RateLimitDecision {
+		allowed,
+		limit: limit.requests,
+		remaining,
+		reset: ceil_secs(used_until - now),
+		retry_after: (!allowed).then(|| ceil_secs(new_tat - now - period)),
+	};
+	(used_until, decision)
+}
+
+fn ceil_secs(millis: u128) -> u64 {
+	millis.div_ceil(1000) as u64
+}
+
+/// Store of rate limiter state.
+///
+/// Implement it for keep state in shared storage (for example, Redis),
+/// see gcra for algorithm.
+pub trait RateLimitStore: Sync + Send {
+	/// Check and count request of client
+	fn check(&self, key: &str, limit: &RateLimit) -> RateLimitDecision;
+}
+
+/// In-memory store of rate limiter
+#[derive(Default)]
+pub struct MemoryRateLimitStore {
+	state: Mutex<MemoryState>,
+}
+
+#[derive(Default)]
+struct MemoryState {
+	/// key -> tat
+	tats: HashMap<String, u128>,
+	/// Time of next removal of expired clients (ms)
+	next_purge: u128,
+}
+
+impl MemoryRateLimitStore {
+	/// Count of clients, after which expired clients are removed
+	const PURGE_THRESHOLD: usize = 10_000;
+	/// Min interval between removals of expired clients (ms)
+	const PURGE_INTERVAL: u128 = 1_000;
+
+	pub fn new() -> MemoryRateLimitStore {
+		MemoryRateLimitStore::default()
+	}
+
+	//inner functions:
+	fn check_at(&self, key: &str, limit: &RateLimit, now: u128) -> RateLimitDecision {
+		let mut state = self.state.lock().unwrap_or_else(|err| err.into_inner());
+		if state.tats.len() >= MemoryRateLimitStore::PURGE_THRESHOLD && now >= state.next_purge {
+			state.tats.retain(|_, tat| *tat > now);
+			state.next_purge = now + MemoryRateLimitStore::PURGE_INTERVAL;
+		}
+		let (tat, decision) = gcra(state.tats.get(key).copied(), now, limit);
+		state.tats.insert(key.to_string(), tat);
+		decision
+	}
+}
+
+impl RateLimitStore for MemoryRateLimitStore {
+	fn check(&self, key: &str, limit: &RateLimit) -> RateLimitDecision {
+		let now = SystemTime::now()
+			.duration_since(UNIX_EPOCH)
+			.unwrap_or_default()
+			.as_millis();
+		self.check_at(key, limit, now)
+	}
+}
+
+/// Rate limiter of requests.
+///
+/// # Examples
+///
+/// ```ignore
+/// RasServiceBuilder::new(runtime, service)
+///     .set_rate_limiter(RateLimiter::new(RateLimit::per_second(100), RateLimitKey::PeerAddr))
+///     .add_post_rate_limiter(
+///         "orders".to_string(),
+///         RateLimiter::new(RateLimit::per_minute(10), RateLimitKey::User)
+///     )
+/// ```
+pub struct RateLimiter {
+	limit: RateLimit,
+	key: RateLimitKey,
+	store: Arc<dyn RateLimitStore>,
+}
+
+impl RateLimiter {
+	/// Create limiter with in-memory store
+	pub fn new(limit: RateLimit, key: RateLimitKey) -> RateLimiter {
+		RateLimiter {
+			limit,
+			key,
+			store: Arc::new(MemoryRateLimitStore::new()),
+		}
+	}
+
+	/// Specify store (in-memory by default)
+	pub fn set_store(mut self, store: Arc<dyn RateLimitStore>) -> Self {
+		self.store = store;
+		self
+	}
+
+	pub fn get_key(&self) -> &RateLimitKey {
+		&self.key
+	}
+
+	/// Check request, scope separates keys of limiters in shared store.
+	///
+	/// If limit is exceeded, response 429 is returned.
+	pub fn check(
+		&self,
+		scope: &str,
+		request: &RequestContext,
+	) -> Result<RateLimitDecision, RasResponse> {
+		let key = format!("{}:{}", scope, self.key.get_key(request));
+		let decision = self.store.check(&key, &self.limit);
+		if decision.allowed {
+			return Ok(decision);
+		}
+		eprintln!("Error! Rate limit is exceeded by {} (request {})", key, request.request_id);
+		Err(RasResponse {
+			headers: decision.get_headers(),
+			..RasResponse::new(HttpStatus::TooManyRequests, None)
+		})
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn expired_clients_are_purged() {
+		let store = MemoryRateLimitStore::new();
+		let limit = RateLimit::per_second(1);
+		let count = || store.state.lock().unwrap().tats.len();
+		for client in 0..MemoryRateLimitStore::PURGE_THRESHOLD {
+			store.check_at(&client.to_string(), &limit, 0);
+		}
+		// clients aren't expired yet
+		store.check_at("new-1", &limit, 500);
+		assert_eq!(count(), MemoryRateLimitStore::PURGE_THRESHOLD + 1);
+		// clients are expired, but next purge isn't earlier than interval
+		store.check_at("new-2", &limit, 1_200);
+		assert_eq!(count(), MemoryRateLimitStore::PURGE_THRESHOLD + 2);
+		store.check_at("new-3", &limit, 3_000);
+		assert_eq!(count(), 1);
+	}
+
+	#[test]
+	fn burst_then_rejection() {
+		let limit = RateLimit::new(2, Duration::from_secs(10));
+		let (tat, decision) = gcra(None, 0, &limit);
+		assert!(decision.allowed);
+		assert_eq!(decision.remaining, 1);
+		let (tat, decision) = gcra(Some(tat), 0, &limit);
+		assert!(decision.allowed);
+		assert_eq!(decision.remaining, 0);
+		assert_eq!(decision.reset, 10);
+		let (tat, decision) = gcra(Some(tat), 1000, &limit);
+		assert!(!decision.allowed);
+		assert_eq!(decision.retry_after, Some(4));
+		let (_, decision) = gcra(Some(tat), 5000, &limit);
+		assert!(decision.allowed);
+
+		let limiter = RateLimiter::new(limit, RateLimitKey::ForwardedFor(1));
+		let request = |addrs: &str| RequestContext {
+			headers: vec![("X-Forwarded-For".to_string(), addrs.to_string())],
+			..RequestContext::default()
+		};
+		assert_eq!(limiter.get_key().get_key(&request("10.0.0.1, 10.0.0.2")), "10.0.0.2");
+		assert_eq!(RateLimitKey::ForwardedFor(2).get_key(&request("10.0.0.1, 10.0.0.2")), "10.0.0.1");
+		assert_eq!(RateLimitKey::ForwardedFor(3).get_key(&request("10.0.0.1, 10.0.0.2")), "unknown");
+		assert!(limiter.check("*", &request("10.0.0.1, 10.0.0.2")).is_ok());
+		assert!(limiter.check("*", &request("10.0.0.3, 10.0.0.2")).is_ok());
+		// spoofed left addresses don't bypass limit
+		let rejection = limiter.check("*", &request("10.0.0.4, 10.0.0.2")).unwrap_err();
+		assert_eq!(rejection.http_status, HttpStatus::TooManyRequests);
+		assert!(rejection.headers.iter().any(|(name, _)| name == "Retry-After"));
+		assert!(limiter.check("*", &RequestContext::default()).is_ok());
+	}
+}
@@ -0,0 +1,49 @@
+use crate::ras_trace;
+
+/// Header of id of request
+pub const REQUEST_ID_HEADER: &str = "X-Request-Id";
+
+/// Max length of incoming id, longer id is replaced
+const MAX_LENGTH: usize = 200;
+
+/// Id from header X-Request-Id or new id.
+///
+/// Incoming id is taken, if it is short and has only visible ASCII chars
+/// (it is written to logs and headers).
+pub fn get_request_id(headers: &[(String, String)]) -> String {
+	let incoming = headers
+		.iter()
+		.find(|(name, _)| name.eq_ignore_ascii_case(REQUEST_ID_HEADER))
+		.map(|(_, value)| value.trim());
+	match incoming {
+		Some(request_id) if !request_id.is_empty()
+			&& request_id.len() <= MAX_LENGTH
+			&& request_id.bytes().all(|char| char.is_ascii_graphic()) => request_id.to_string(),
+		_ => new_request_id(),
+	}
+}
+
+/// Random id in format of UUID v4
+pub fn new_request_id() -> String {
+	let mut bytes: [u8; 16] = ras_trace::random_id();
+	bytes[6] = (bytes[6] & 0x0f) | 0x40;
+	bytes[8] = (bytes[8] & 0x3f) | 0x80;
+	let hex: String = bytes.iter().map(|byte| format!("{:02x}", byte)).collect();
+	format!("{}-{}-{}-{}-{}", &hex[..8], &hex[8..12], &hex[12..16], &hex[16..20], &hex[20..])
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn incoming_id_is_validated() {
+		let headers = |value: &str| vec![("x-request-id".to_string(), value.to_string())];
+		assert_eq!(get_request_id(&headers(" abc-123 ")), "abc-123");
+		let request_id = get_request_id(&headers("bad id\r\nX-Admin: 1"));
+		assert_eq!(request_id.len(), 36);
+		assert_eq!(&request_id[14..15], "4");
+		assert_ne!(get_request_id(&[]), get_request_id(&[]));
+		assert_eq!(get_request_id(&headers(&"a".repeat(201))).len(), 36);
+	}
+}
@@ -0,0 +1,160 @@
+use crate::{
+	Arc,
+	Handle,
+	ras_auth_client::TokenSession,
+};
+use reqwest::{Client, RequestBuilder, Response};
+use std::time::Duration;
+use tokio::task::JoinHandle;
+
+/// Client for calls to other services with role Service.
+///
+/// Logs into ras_auth with service credentials, caches access token
+/// and renews it before end of life time (by refresh token, if ras_auth returns it).
+///
+/// # Examples
+///
+/// ```ignore
+/// let auth_client = ServiceAuthClient::new(login, password, ras_auth_uri);
+/// let response = auth_client
+///     .send(auth_client.http_client().post("http://orders/api/create").body(data))
+///     .await?;
+/// ```
+pub struct ServiceAuthClient {
+	session: TokenSession,
+	renew_before: u128,
+}
+
+impl ServiceAuthClient {
+	//constructor:
+	pub fn new(
+		login: String,
+		password: String,
+		ras_auth_uri: String,
+	) -> ServiceAuthClient {
+		ServiceAuthClient {
+			session: TokenSession::new(ras_auth_uri).set_credentials(login, password),
+			renew_before: 5_000_u128,
+		}
+	}
+
+	/// Specify life time of token in ms (as get_life_time_token of RasAuthClient).
+	pub fn set_token_life_time(mut self, token_life_time: u128) -> Self {
+		self.session = self.session.set_token_life_time(token_life_time);
+		self
+	}
+
+	/// Specify, how long before end of life time token is renewed (in ms).
+	pub fn set_renew_before(mut self, renew_before: u128) -> Self {
+		self.renew_before = renew_before;
+		self.session = self.session.set_renew_before(renew_before);
+		self
+	}
+
+	/// Specify http client for calls
+	pub fn set_http_client(mut self, client: Client) -> Self {
+		self.session = self.session.set_http_client(client);
+		self
+	}
+
+	//interface:
+	/// Http client for build requests
+	pub fn http_client(&self) -> &Client {
+		self.session.http_client()
+	}
+
+	/// Session with tokens of service
+	pub fn session(&self) -> &TokenSession {
+		&self.session
+	}
+
+	/// Return cached access token or get new token from ras_auth.
+	pub async fn get_access_token(&self) -> Result<String, ()> {
+		self.session.get_access_token().await
+	}
+
+	/// Attach access token to request (header Authorization: Bearer).
+	pub async fn authorize(&self, request: RequestBuilder) -> Result<RequestBuilder, ()> {
+		self.session.authorize(request).await
+	}
+
+	/// Send request with access token.
+	///
+	/// If service answers 401 or 419, token is renewed and request is retried once.
+	pub async fn send(&self, request: RequestBuilder) -> Result<Response, ()> {
+		self.session.send_with_retry(request, &[401, 419]).await
+	}
+
+	/// Start renewing token in background before end of its life time.
+	///
+	/// Token is checked every renew_before / 2 ms,
+	/// so it is renewed, when less than renew_before ms of life time remains.
+	pub fn spawn_renewal(self: &Arc<Self>, runtime: &Handle) -> JoinHandle<()> {
+		let auth_client = self.clone();
+		let interval = Duration::from_millis((auth_client.renew_before / 2).max(1) as u64);
+		runtime.spawn(async move {
+			let mut timer = tokio::time::interval(interval);
+			loop {
+				timer.tick().await;
+				if auth_client.get_access_token().await.is_err() {
+					eprintln!("Error! Can't renew access token of service");
+				}
+			}
+		})
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::ras_auth_client::tests::StubAuthServer;
+
+	#[test]
+	fn token_is_cached_and_renewed() {
+		let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+		runtime.block_on(async {
+			let server = StubAuthServer::start().await;
+			let auth_client = ServiceAuthClient::new(
+				"service".to_string(),
+				"password".to_string(),
+				server.uri.clone(),
+			)
+				.set_token_life_time(300)
+				.set_renew_before(100);
+			assert_eq!(auth_client.get_access_token().await.unwrap(), "access-1");
+			assert_eq!(auth_client.get_access_token().await.unwrap(), "access-1");
+			assert_eq!(server.calls(), ["/login"]);
+
+			// token is renewed by refresh token before end of life time
+			tokio::time::sleep(Duration::from_millis(250)).await;
+			assert_eq!(auth_client.get_access_token().await.unwrap(), "refreshed-1");
+			assert_eq!(auth_client.get_access_token().await.unwrap(), "refreshed-1");
+			assert_eq!(server.calls(), ["/login", "/refresh"]);
+		});
+	}
+
+	#[test]
+	fn token_is_renewed_in_background() {
+		let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+		let handle = runtime.handle().clone();
+		runtime.block_on(async {
+			let server = StubAuthServer::start().await;
+			let auth_client = Arc::new(ServiceAuthClient::new(
+				"service".to_string(),
+				"password".to_string(),
+				server.uri.clone(),
+			)
+				.set_token_life_time(600)
+				.set_renew_before(300));
+			assert_eq!(auth_client.get_access_token().await.unwrap(), "access-1");
+			let renewal = auth_client.spawn_renewal(&handle);
+
+			// token is renewed without callers before end of life time
+			tokio::time::sleep(Duration::from_millis(500)).await;
+			assert_eq!(server.calls(), ["/login", "/refresh"]);
+			assert_eq!(auth_client.get_access_token().await.unwrap(), "refreshed-1");
+			assert_eq!(server.calls(), ["/login", "/refresh"]);
+			renewal.abort();
+		});
+	}
+}
@@ -0,0 +1,696 @@
+use crate::{
+	Arc,
+	HashMap,
+	Handle,
+	JoinHandle,
+};
+use openssl::{
+	pkey::PKey,
+	ssl::{
+		AlpnError,
+		NameType,
+		SniError,
+		Ssl,
+		SslAcceptor,
+		SslAcceptorBuilder,
+		SslMethod,
+		SslVerifyMode,
+	},
+	x509::{X509, X509Ref, store::X509StoreBuilder},
+};
+use std::{
+	pin::Pin,
+	sync::{Mutex, RwLock},
+	time::{Duration, SystemTime},
+};
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio_openssl::SslStream;
+
+/// Certificate of client (for mutual TLS).
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PeerCertificate {
+	/// Subject, for example "CN=orders,O=Acme"
+	pub subject: String,
+	pub common_name: Option<String>,
+	/// Subject alternative names (DNS, URI, email and IP)
+	pub subject_alt_names: Vec<String>,
+}
+
+impl PeerCertificate {
+	pub fn new_from_x509(cert: &X509Ref) -> PeerCertificate {
+		let mut common_name = None;
+		let subject = cert.subject_name()
+			.entries()
+			.map(|entry| {
+				let name = entry.object().nid().short_name().unwrap_or("?");
+				let value = entry.data()
+					.to_string()
+					.unwrap_or_default();
+				if name == "CN" && common_name.is_none() {
+					common_name = Some(value.clone());
+				}
+				format!("{}={}", name, value)
+			})
+			.collect::<Vec<String>>()
+			.join(",");
+		let subject_alt_names = cert.subject_alt_names()
+			.map(|names| names
+				.iter()
+				.filter_map(|name| {
+					if let Some(dns) = name.dnsname() {
+						return Some(dns.to_string());
+					}
+					if let Some(uri) = name.uri() {
+						return Some(uri.to_string());
+					}
+					if let Some(email) = name.email() {
+						return Some(email.to_string());
+					}
+					name.ipaddress().and_then(|ip| match ip.len() {
+						4 => <[u8; 4]>::try_from(ip)
+							.ok()
+							.map(|ip| std::net::IpAddr::from(ip).to_string()),
+						16 => <[u8; 16]>::try_from(ip)
+							.ok()
+							.map(|ip| std::net::IpAddr::from(ip).to_string()),
+						_ => None,
+					})
+				})
+				.collect())
+			.unwrap_or_default();
+		PeerCertificate {
+			subject,
+			common_name,
+			subject_alt_names,
+		}
+	}
+
+	/// Check common name and subject alternative names
+	pub fn has_identity(&self, identity: &str) -> bool {
+		self.common_name.as_deref() == Some(identity)
+			|| self.subject_alt_names.iter().any(|name| name == identity)
+	}
+}
+
+/// Source of PEM data
+enum PemSource {
+	Data(Vec<u8>),
+	File(String),
+}
+
+impl PemSource {
+	fn read(&self) -> Result<Vec<u8>, ()> {
+		match self {
+			PemSource::Data(data) => Ok(data.clone()),
+			PemSource::File(path) => std::fs::read(path).map_err(|err| {
+				eprintln!("Error! Can't read PEM file {}: {:?}", path, err);
+			}),
+		}
+	}
+
+	fn modified(&self) -> Option<SystemTime> {
+		match self {
+			PemSource::Data(_) => None,
+			PemSource::File(path) => std::fs::metadata(path)
+				.and_then(|metadata| metadata.modified())
+				.ok(),
+		}
+	}
+}
+
+/// Certificate chain and private key
+struct CertificateSource {
+	cert_chain: PemSource,
+	private_key: PemSource,
+}
+
+/// TLS settings of listener.
+///
+/// Certificate is selected by SNI (see add_sni_certificate),
+/// default certificate is used for other names.
+/// ALPN "http/1.1" is advertised ("h2" also, with feature "Http2").
+///
+/// Certificates, loaded from files, are reloaded,
+/// when files are changed (see spawn_reload).
+///
+/// # Examples
+///
+/// ```ignore
+/// let tls_config = TlsConfig::new_with_client_ca(
+///     &cert_chain_pem,
+///     &private_key_pem,
+///     &ca_bundle_pem,
+///     true
+/// )?;
+/// RasServiceBuilder::new(runtime, service)
+///     .set_tls_config(tls_config)
+/// ```
+pub struct TlsConfig {
+	certificate: CertificateSource,
+	sni_certificates: Vec<(String, CertificateSource)>,
+	client_ca: Option<(Vec<u8>, bool)>,
+	alpn_protocols: Vec<String>,
+	reload_interval: Duration,
+	acceptor: RwLock<SslAcceptor>,
+	modified: Mutex<Option<SystemTime>>,
+}
+
+impl TlsConfig {
+	//constructors:
+	/// Create settings from certificate chain and private key (PEM).
+	pub fn new(cert_chain: &[u8], private_key: &[u8]) -> Result<TlsConfig, ()> {
+		TlsConfig::build(
+			CertificateSource {
+				cert_chain: PemSource::Data(cert_chain.to_vec()),
+				private_key: PemSource::Data(private_key.to_vec()),
+			},
+			None,
+		)
+	}
+
+	/// Create settings from certificate chain and private key,
+	/// client certificates are verified by CA bundle (PEM).
+	///
+	/// If required, connection without client certificate is rejected.
+	pub fn new_with_client_ca(
+		cert_chain: &[u8],
+		private_key: &[u8],
+		ca_bundle: &[u8],
+		required: bool,
+	) -> Result<TlsConfig, ()> {
+		TlsConfig::build(
+			CertificateSource {
+				cert_chain: PemSource::Data(cert_chain.to_vec()),
+				private_key: PemSource::Data(private_key.to_vec()),
+			},
+			Some((ca_bundle.to_vec(), required)),
+		)
+	}
+
+	/// Create settings from PEM files of certificate chain and private key.
+	///
+	/// Files are reloaded, when they are changed.
+	pub fn new_from_files(
+		cert_chain_path: &str,
+		private_key_path: &str,
+	) -> Result<TlsConfig, ()> {
+		TlsConfig::build(
+			CertificateSource {
+				cert_chain: PemSource::File(cert_chain_path.to_string()),
+				private_key: PemSource::File(private_key_path.to_string()),
+			},
+			None,
+		)
+	}
+
+	fn build(
+		certificate: CertificateSource,
+		client_ca: Option<(Vec<u8>, bool)>,
+	) -> Result<TlsConfig, ()> {
+		let alpn_protocols = match cfg!(feature = "Http2") {
+			true => vec!["h2".to_string(), "http/1.1".to_string()],
+			false => vec!["http/1.1".to_string()],
+		};
+		let acceptor = load_acceptor(&certificate, &[], &client_ca, &alpn_protocols)?;
+		let tls_config = TlsConfig {
+			certificate,
+			sni_certificates: Vec::new(),
+			client_ca,
+			alpn_protocols,
+			reload_interval: Duration::from_secs(30),
+			acceptor: RwLock::new(acceptor),
+			modified: Mutex::new(None),
+		};
+		*tls_config.modified.lock().unwrap_or_else(|err| err.into_inner()) =
+			tls_config.get_modified();
+		Ok(tls_config)
+	}
+
+	/// Add certificate for server name (SNI).
+	///
+	/// Name can be wildcard, for example "*.example.com".
+	pub fn add_sni_certificate(
+		self,
+		server_name: &str,
+		cert_chain: &[u8],
+		private_key: &[u8],
+	) -> Result<Self, ()> {
+		self.add_sni_source(server_name, CertificateSource {
+			cert_chain: PemSource::Data(cert_chain.to_vec()),
+			private_key: PemSource::Data(private_key.to_vec()),
+		})
+	}
+
+	/// Add certificate for server name (SNI) from PEM files
+	pub fn add_sni_certificate_files(
+		self,
+		server_name: &str,
+		cert_chain_path: &str,
+		private_key_path: &str,
+	) -> Result<Self, ()> {
+		self.add_sni_source(server_name, CertificateSource {
+			cert_chain: PemSource::File(cert_chain_path.to_string()),
+			private_key: PemSource::File(private_key_path.to_string()),
+		})
+	}
+
+	/// Specify interval of checking files of certificates (30 seconds by default)
+	pub fn set_reload_interval(mut self, reload_interval: Duration) -> Self {
+		self.reload_interval = reload_interval;
+		self
+	}
+
+	//interface:
+	/// Load certificates again.
+	///
+	/// If certificates are bad, old certificates stay in use.
+	pub fn reload(&self) -> Result<(), ()> {
+		let modified = self.get_modified();
+		let acceptor = load_acceptor(
+			&self.certificate,
+			&self.sni_certificates,
+			&self.client_ca,
+			&self.alpn_protocols,
+		)?;
+		*self.acceptor.write().unwrap_or_else(|err| err.into_inner()) = acceptor;
+		*self.modified.lock().unwrap_or_else(|err| err.into_inner()) = modified;
+		Ok(())
+	}
+
+	/// Start checking files of certificates in background,
+	/// certificates are reloaded, when files are changed.
+	pub fn spawn_reload(self: &Arc<Self>, runtime: &Handle) -> JoinHandle<()> {
+		let tls_config = self.clone();
+		runtime.spawn(async move {
+			let mut timer = tokio::time::interval(tls_config.reload_interval);
+			loop {
+				timer.tick().await;
+				let modified = tls_config.get_modified();
+				if modified == *tls_config.modified.lock().unwrap_or_else(|err| err.into_inner()) {
+					continue;
+				}
+				match tls_config.reload() {
+					Ok(_) => eprintln!("Certificates are reloaded"),
+					Err(_) => eprintln!("Error! Can't reload certificates"),
+				}
+			}
+		})
+	}
+
+	/// TLS handshake, return stream and certificate of client.
+	///
+	/// Handshake is failed, if it isn't finished in timeout (None - without timeout).
+	pub async fn accept<S>(
+		&self,
+		stream: S,
+		timeout: Option<Duration>,
+	) -> Result<(SslStream<S>, Option<PeerCertificate>), ()>
+	where S: AsyncRead + AsyncWrite + Unpin {
+		let ssl = match Ssl::new(
+			self.acceptor.read().unwrap_or_else(|err| err.into_inner()).context()
+		) {
+			Ok(ssl) => ssl,
+			Err(err) => {
+				eprintln!("Error! Can't create TLS session: {}", err);
+				return Err(());
+			}
+		};
+		let mut tls_stream = match SslStream::new(ssl, stream) {
+			Ok(tls_stream) => tls_stream,
+			Err(err) => {
+				eprintln!("Error! Can't create TLS stream: {}", err);
+				return Err(());
+			}
+		};
+		let handshake = Pin::new(&mut tls_stream).accept();
+		let handshake = match timeout {
+			Some(timeout) => match tokio::time::timeout(timeout, handshake).await {
+				Ok(handshake) => handshake,
+				Err(_) => {
+					eprintln!("Error! Timeout of TLS handshake");
+					return Err(());
+				}
+			},
+			None => handshake.await,
+		};
+		if let Err(err) = handshake {
+			eprintln!("Error! TLS handshake failed: {}", err);
+			return Err(());
+		}
+		let peer_certificate = tls_stream.ssl()
+			.peer_certificate()
+			.map(|cert| PeerCertificate::new_from_x509(&cert));
+		Ok((tls_stream, peer_certificate))
+	}
+
+	//inner functions:
+	fn add_sni_source(
+		mut self,
+		server_name: &str,
+		certificate: CertificateSource,
+	) -> Result<Self, ()> {
+		self.sni_certificates.push((server_name.to_lowercase(), certificate));
+		self.reload()?;
+		Ok(self)
+	}
+
+	/// Last modification time of files
+	fn get_modified(&self) -> Option<SystemTime> {
+		std::iter::once(&self.certificate)
+			.chain(self.sni_certificates.iter().map(|(_, certificate)| certificate))
+			.flat_map(|certificate| [
+				certificate.cert_chain.modified(),
+				certificate.private_key.modified(),
+			])
+			.flatten()
+			.max()
+	}
+}
+
+fn load_acceptor(
+	certificate: &CertificateSource,
+	sni_certificates: &[(String, CertificateSource)],
+	client_ca: &Option<(Vec<u8>, bool)>,
+	alpn_protocols: &[String],
+) -> Result<SslAcceptor, ()> {
+	let client_ca = client_ca
+		.as_ref()
+		.map(|(ca_bundle, required)| (ca_bundle.as_slice(), *required));
+	let mut sni_contexts = HashMap::new();
+	for (server_name, sni_certificate) in sni_certificates {
+		let builder = load_acceptor_builder(sni_certificate, client_ca, alpn_protocols)?;
+		sni_contexts.insert(server_name.clone(), builder.build().into_context());
+	}
+	let mut builder = load_acceptor_builder(certificate, client_ca, alpn_protocols)?;
+	if !sni_contexts.is_empty() {
+		builder.set_servername_callback(move |ssl, _| {
+			let server_name = match ssl.servername(NameType::HOST_NAME) {
+				Some(server_name) => server_name.to_lowercase(),
+				None => return Ok(()),
+			};
+			let wildcard = server_name
+				.split_once('.')
+				.map(|(_, domain)| format!("*.{}", domain));
+			let context = sni_contexts
+				.get(&server_name)
+				.or_else(|| wildcard.and_then(|wildcard| sni_contexts.get(&wildcard)));
+			if let Some(context) = context {
+				if let Err(err) = ssl.set_ssl_context(context) {
+					eprintln!("Error! Can't select certificate for {}: {}", server_name, err);
+					return Err(SniError::ALERT_FATAL);
+				}
+			}
+			Ok(())
+		});
+	}
+	Ok(builder.build())
+}
+
+fn load_acceptor_builder(
+	certificate: &CertificateSource,
+	client_ca: Option<(&[u8], bool)>,
+	alpn_protocols: &[String],
+) -> Result<SslAcceptorBuilder, ()> {
+	let cert_chain = match X509::stack_from_pem(&certificate.cert_chain.read()?) {
+		Ok(cert_chain) if !cert_chain.is_empty() => cert_chain,
+		Ok(_) => {
+			eprintln!("Error! Certificate chain is empty");
+			return Err(());
+		},
+		Err(err) => {
+			eprintln!("Error! Bad certificate chain: {}", err);
+			return Err(());
+		}
+	};
+	let private_key = certificate.private_key.read()?;
+	build_acceptor(cert_chain, &private_key, client_ca, alpn_protocols)
+		.map_err(|err| {
+			eprintln!("Error! Can't build TLS acceptor: {}", err);
+		})
+}
+
+fn build_acceptor(
+	cert_chain: Vec<X509>,
+	private_key: &[u8],
+	client_ca: Option<(&[u8], bool)>,
+	alpn_protocols: &[String],
+) -> Result<SslAcceptorBuilder, openssl::error::ErrorStack> {
+	let mut builder = SslAcceptor::mozilla_intermediate_v5(SslMethod::tls_server())?;
+	for (index, cert) in cert_chain.into_iter().enumerate() {
+		match index {
+			0 => builder.set_certificate(&cert)?,
+			_ => builder.add_extra_chain_cert(cert)?,
+		}
+	}
+	let private_key = PKey::private_key_from_pem(private_key)?;
+	builder.set_private_key(&private_key)?;
+	builder.check_private_key()?;
+	if let Some((ca_bundle, required)) = client_ca {
+		let mut store = X509StoreBuilder::new()?;
+		for ca in X509::stack_from_pem(ca_bundle)? {
+			builder.add_client_ca(&ca)?;
+			store.add_cert(ca)?;
+		}
+		builder.set_verify_cert_store(store.build())?;
+		builder.set_verify(if required {
+			SslVerifyMode::PEER | SslVerifyMode::FAIL_IF_NO_PEER_CERT
+		} else {
+			SslVerifyMode::PEER
+		});
+	}
+	let alpn_protocols = alpn_protocols.to_vec();
+	builder.set_alpn_select_callback(move |_, client_protocols| {
+		select_alpn_protocol(&alpn_protocols, client_protocols).ok_or(AlpnError::NOACK)
+	});
+	Ok(builder)
+}
+
+/// Select first protocol of server, which is offered by client
+fn select_alpn_protocol<'a>(
+	protocols: &[String],
+	client_protocols: &'a [u8],
+) -> Option<&'a [u8]> {
+	let mut offered = Vec::new();
+	let mut rest = client_protocols;
+	while let Some((&len, tail)) = rest.split_first() {
+		if tail.len() < len as usize {
+			break;
+		}
+		let (protocol, tail) = tail.split_at(len as usize);
+		offered.push(protocol);
+		rest = tail;
+	}
+	protocols
+		.iter()
+		.find_map(|protocol| offered
+			.iter()
+			.find(|offered| **offered == protocol.as_bytes())
+			.copied())
+}
+
+/// Authenticator by certificate of client (mutual TLS).
+///
+/// Principal is selected by common name or subject alternative name of certificate.
+#[cfg(feature = "Authentication")]
+pub struct ClientCertAuthenticator {
+	identities: HashMap<String, crate::ras_auth_layer::Principal>,
+}
+
+#[cfg(feature = "Authentication")]
+impl ClientCertAuthenticator {
+	pub fn new() -> ClientCertAuthenticator {
+		ClientCertAuthenticator {
+			identities: HashMap::new(),
+		}
+	}
+
+	/// Add principal for identity (common name or subject alternative name)
+	pub fn add_identity(
+		mut self,
+		identity: &str,
+		principal: crate::ras_auth_layer::Principal,
+	) -> Self {
+		self.identities.insert(identity.to_string(), principal);
+		self
+	}
+}
+
+#[cfg(feature = "Authentication")]
+impl Default for ClientCertAuthenticator {
+	fn default() -> Self {
+		ClientCertAuthenticator::new()
+	}
+}
+
+#[cfg(feature = "Authentication")]
+impl crate::ras_auth_layer::Authenticator for ClientCertAuthenticator {
+	fn authenticate(
+		&self,
+		request: &crate::RequestContext,
+	) -> crate::ras_auth_layer::AuthResult {
+		use crate::ras_auth_layer::AuthResult;
+		let peer_certificate = match request.peer_certificate {
+			Some(ref peer_certificate) => peer_certificate,
+			None => return AuthResult::NoCredentials,
+		};
+		match self.identities
+			.iter()
+			.find(|(identity, _)| peer_certificate.has_identity(identity)) {
+			Some((_, principal)) => AuthResult::Authenticated(principal.clone()),
+			None => {
+				eprintln!("Error! Unknown client certificate: {}", peer_certificate.subject);
+				AuthResult::Rejected(crate::HttpStatus::Forbidden)
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use openssl::{
+		asn1::Asn1Time,
+		bn::BigNum,
+		hash::MessageDigest,
+		pkey::Private,
+		rsa::Rsa,
+		ssl::SslConnector,
+		x509::{X509NameBuilder, extension::{BasicConstraints, SubjectAlternativeName}},
+	};
+
+	fn generate_cert(
+		common_name: &str,
+		issuer: Option<(&X509, &PKey<Private>)>,
+	) -> (X509, PKey<Private>) {
+		let key = PKey::from_rsa(Rsa::generate(2048).unwrap()).unwrap();
+		let mut name = X509NameBuilder::new().unwrap();
+		name.append_entry_by_text("CN", common_name).unwrap();
+		let name = name.build();
+		let mut builder = X509::builder().unwrap();
+		builder.set_version(2).unwrap();
+		builder.set_serial_number(&BigNum::from_u32(1).unwrap().to_asn1_integer().unwrap()).unwrap();
+		builder.set_subject_name(&name).unwrap();
+		builder.set_pubkey(&key).unwrap();
+		builder.set_not_before(&Asn1Time::days_from_now(0).unwrap()).unwrap();
+		builder.set_not_after(&Asn1Time::days_from_now(1).unwrap()).unwrap();
+		match issuer {
+			Some((issuer_cert, issuer_key)) => {
+				builder.set_issuer_name(issuer_cert.subject_name()).unwrap();
+				let san = SubjectAlternativeName::new()
+					.dns(common_name)
+					.uri("spiffe://acme/orders")
+					.build(&builder.x509v3_context(Some(issuer_cert), None))
+					.unwrap();
+				builder.append_extension(san).unwrap();
+				builder.sign(issuer_key, MessageDigest::sha256()).unwrap();
+			},
+			None => {
+				builder.set_issuer_name(&name).unwrap();
+				builder.append_extension(BasicConstraints::new().critical().ca().build().unwrap())
+					.unwrap();
+				builder.sign(&key, MessageDigest::sha256()).unwrap();
+			}
+		}
+		(builder.build(), key)
+	}
+
+	#[test]
+	fn client_certificate_is_verified() {
+		let (ca_cert, ca_key) = generate_cert("ca", None);
+		let (server_cert, server_key) = generate_cert("localhost", Some((&ca_cert, &ca_key)));
+		let (client_cert, client_key) = generate_cert("orders", Some((&ca_cert, &ca_key)));
+		let tls_config = TlsConfig::new_with_client_ca(
+			&server_cert.to_pem().unwrap(),
+			&server_key.private_key_to_pem_pkcs8().unwrap(),
+			&ca_cert.to_pem().unwrap(),
+			true,
+		).unwrap();
+
+		let mut connector = SslConnector::builder(SslMethod::tls_client()).unwrap();
+		connector.set_certificate(&client_cert).unwrap();
+		connector.set_private_key(&client_key).unwrap();
+		connector.cert_store_mut().add_cert(ca_cert).unwrap();
+		let ssl = connector.build().configure().unwrap().into_ssl("localhost").unwrap();
+
+		let runtime = tokio::runtime::Builder::new_current_thread().enable_time().build().unwrap();
+		let peer_certificate = runtime.block_on(async move {
+			// client, which doesn't send handshake, is rejected by timeout
+			let (_client_stream, server_stream) = tokio::io::duplex(16 * 1024);
+			let timeout = Some(Duration::from_millis(50));
+			assert!(tls_config.accept(server_stream, timeout).await.is_err());
+
+			let (client_stream, server_stream) = tokio::io::duplex(16 * 1024);
+			let client = tokio::spawn(async move {
+				let mut tls_stream = SslStream::new(ssl, client_stream).unwrap();
+				Pin::new(&mut tls_stream).connect().await.unwrap();
+				tls_stream
+			});
+			let (_tls_stream, peer_certificate) = tls_config.accept(server_stream, None).await.unwrap();
+			client.await.unwrap();
+			peer_certificate
+		}).unwrap();
+		assert_eq!(peer_certificate.subject, "CN=orders");
+		assert_eq!(peer_certificate.common_name.as_deref(), Some("orders"));
+		assert!(peer_certificate.has_identity("spiffe://acme/orders"));
+	}
+
+	fn handshake(tls_config: &TlsConfig, server_name: &str) -> (String, Option<Vec<u8>>) {
+		let mut connector = SslConnector::builder(SslMethod::tls_client()).unwrap();
+		connector.set_verify(SslVerifyMode::NONE);
+		connector.set_alpn_protos(b"\x02h2\x08http/1.1").unwrap();
+		let ssl = connector.build().configure().unwrap().into_ssl(server_name).unwrap();
+		let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+		runtime.block_on(async move {
+			let (client_stream, server_stream) = tokio::io::duplex(16 * 1024);
+			let client = tokio::spawn(async move {
+				let mut tls_stream = SslStream::new(ssl, client_stream).unwrap();
+				Pin::new(&mut tls_stream).connect().await.unwrap();
+				let common_name = PeerCertificate::new_from_x509(
+					&tls_stream.ssl().peer_certificate().unwrap()
+				).common_name.unwrap();
+				let alpn = tls_stream.ssl().selected_alpn_protocol().map(|alpn| alpn.to_vec());
+				(common_name, alpn, tls_stream)
+			});
+			let _tls_stream = tls_config.accept(server_stream, None).await.unwrap();
+			let (common_name, alpn, _tls_stream) = client.await.unwrap();
+			(common_name, alpn)
+		})
+	}
+
+	#[test]
+	fn certificate_by_sni_and_reload() {
+		let (ca_cert, ca_key) = generate_cert("ca", None);
+		let (cert, key) = generate_cert("default.local", Some((&ca_cert, &ca_key)));
+		let (sni_cert, sni_key) = generate_cert("api.local", Some((&ca_cert, &ca_key)));
+		let dir = std::env::temp_dir().join(format!("ras_tls_{}", std::process::id()));
+		std::fs::create_dir_all(&dir).unwrap();
+		let cert_path = dir.join("cert.pem");
+		let key_path = dir.join("key.pem");
+		std::fs::write(&cert_path, cert.to_pem().unwrap()).unwrap();
+		std::fs::write(&key_path, key.private_key_to_pem_pkcs8().unwrap()).unwrap();
+		let tls_config = TlsConfig::new_from_files(
+			cert_path.to_str().unwrap(),
+			key_path.to_str().unwrap(),
+		).unwrap()
+			.add_sni_certificate(
+				"*.local",
+				&sni_cert.to_pem().unwrap(),
+				&sni_key.private_key_to_pem_pkcs8().unwrap(),
+			).unwrap();
+		let (common_name, alpn) = handshake(&tls_config, "api.local");
+		assert_eq!(common_name, "api.local");
+		let expected_alpn = match cfg!(feature = "Http2") {
+			true => &b"h2"[..],
+			false => &b"http/1.1"[..],
+		};
+		assert_eq!(alpn.as_deref(), Some(expected_alpn));
+		assert_eq!(handshake(&tls_config, "localhost").0, "default.local");
+
+		let (new_cert, new_key) = generate_cert("renewed.local", Some((&ca_cert, &ca_key)));
+		std::fs::write(&cert_path, new_cert.to_pem().unwrap()).unwrap();
+		std::fs::write(&key_path, new_key.private_key_to_pem_pkcs8().unwrap()).unwrap();
+		tls_config.reload().unwrap();
+		assert_eq!(handshake(&tls_config, "localhost").0, "renewed.local");
+		std::fs::remove_dir_all(&dir).unwrap();
+	}
+}
@@ -0,0 +1,324 @@
+use crate::{
+	MessageDigest,
+	PKey,
+	ras_auth_client::Token,
+	ras_jwks::JwtHeader,
+};
+use openssl::{
+	ecdsa::EcdsaSig,
+	nid::Nid,
+	pkey::{Id, Private},
+	sign::Signer,
+};
+use serde::Serialize;
+
+/// Signer of tokens.
+///
+/// # Examples
+///
+/// ```ignore
+/// let signer = TokenSigner::new(private_key)
+///     .set_key_id("key-1");
+/// let token = signer.sign(&AccessToken::new("user".to_string(), ROLE_SERVICE))?;
+/// ```
+pub struct TokenSigner {
+	private_key: PKey<Private>,
+	digest: MessageDigest,
+	key_id: Option<String>,
+}
+
+impl TokenSigner {
+	//constructor:
+	/// Create signer with digest sha256
+	pub fn new(private_key: PKey<Private>) -> TokenSigner {
+		TokenSigner {
+			private_key,
+			digest: MessageDigest::sha256(),
+			key_id: None,
+		}
+	}
+
+	/// Specify digest (sha256 by default).
+	///
+	/// For JWT only sha256, sha384 and sha512 are supported,
+	/// for EC key digest must match curve (P-256 - sha256, P-384 - sha384, P-521 - sha512).
+	pub fn set_digest(mut self, digest: MessageDigest) -> Self {
+		self.digest = digest;
+		self
+	}
+
+	/// Specify key id, which is added to token (see KeyStore)
+	pub fn set_key_id(mut self, key_id: &str) -> Self {
+		self.key_id = Some(key_id.to_string());
+		self
+	}
+
+	//interface:
+	/// Create token in format json@@sign (or json@@sign@@key_id, if key id is specified).
+	///
+	/// Ed25519 and Ed448 keys are supported only for JWT.
+	pub fn sign<T: Token + Serialize>(&self, token: &T) -> Result<String, ()> {
+		if let Id::ED25519 | Id::ED448 = self.private_key.id() {
+			eprintln!("Error! Ed25519 and Ed448 keys can't sign token, only JWT");
+			return Err(());
+		}
+		let b64_json = token.get_b64()?;
+		let sign = match self.sign_data(b64_json.as_bytes()) {
+			Ok(sign) => base64::encode(sign),
+			Err(err) => {
+				eprintln!("Error! Can't sign token: {}", err);
+				return Err(());
+			}
+		};
+		Ok(match self.key_id {
+			Some(ref key_id) => format!("{}@@{}@@{}", b64_json, sign, key_id),
+			None => format!("{}@@{}", b64_json, sign),
+		})
+	}
+
+	/// Create JWT with claims.
+	///
+	/// Algorithm is selected by type of key:
+	/// RSA - RS* by digest, EC - ES* by curve, Ed25519 and Ed448 - EdDSA.
+	pub fn sign_jwt<C: Serialize>(&self, claims: &C) -> Result<String, ()> {
+		let header = JwtHeader {
+			alg: self.get_jws_alg()?,
+			kid: self.key_id.clone(),
+			typ: Some("JWT".to_string()),
+		};
+		let (header, claims) = match (serde_json::to_vec(&header), serde_json::to_vec(claims)) {
+			(Ok(header), Ok(claims)) => (header, claims),
+			_ => {
+				eprintln!("Error! Can't convert claims of JWT to json");
+				return Err(());
+			}
+		};
+		let signed_data = format!("{}.{}", encode_b64url(&header), encode_b64url(&claims));
+		let mut sign = match self.sign_data(signed_data.as_bytes()) {
+			Ok(sign) => sign,
+			Err(err) => {
+				eprintln!("Error! Can't sign JWT: {}", err);
+				return Err(());
+			}
+		};
+		if self.private_key.id() == Id::EC {
+			sign = self.ecdsa_sign_from_der(&sign)?;
+		}
+		Ok(format!("{}.{}", signed_data, encode_b64url(&sign)))
+	}
+
+	//inner functions:
+	fn sign_data(&self, data: &[u8]) -> Result<Vec<u8>, openssl::error::ErrorStack> {
+		match self.private_key.id() {
+			Id::ED25519 | Id::ED448 => Signer::new_without_digest(&self.private_key)?
+				.sign_oneshot_to_vec(data),
+			_ => {
+				let mut signer = Signer::new(self.digest, &self.private_key)?;
+				signer.update(data)?;
+				signer.sign_to_vec()
+			}
+		}
+	}
+
+	fn get_jws_alg(&self) -> Result<String, ()> {
+		if let Id::ED25519 | Id::ED448 = self.private_key.id() {
+			return Ok("EdDSA".to_string());
+		}
+		let digest_size = match self.digest.type_() {
+			nid if nid == MessageDigest::sha256().type_() => "256",
+			nid if nid == MessageDigest::sha384().type_() => "384",
+			nid if nid == MessageDigest::sha512().type_() => "512",
+			nid => {
+				eprintln!("Error! Unsupported digest for JWT: {:?}", nid);
+				return Err(());
+			}
+		};
+		match self.private_key.id() {
+			Id::RSA => Ok(format!("RS{}", digest_size)),
+			Id::EC => {
+				let curve = self.private_key.ec_key().ok()
+					.and_then(|ec_key| ec_key.group().curve_name());
+				let curve_size = match curve {
+					Some(Nid::X9_62_PRIME256V1) => "256",
+					Some(Nid::SECP384R1) => "384",
+					Some(Nid::SECP521R1) => "512",
+					curve => {
+						eprintln!("Error! Unsupported curve for JWT: {:?}", curve);
+						return Err(());
+					}
+				};
+				if curve_size != digest_size {
+					eprintln!("Error! Digest sha{} doesn't match curve of key for ES{}", digest_size, curve_size);
+					return Err(());
+				}
+				Ok(format!("ES{}", curve_size))
+			},
+			id => {
+				eprintln!("Error! Unsupported key type for JWT: {:?}", id);
+				Err(())
+			}
+		}
+	}
+
+	/// JWS ECDSA sign is r||s with size of curve
+	fn ecdsa_sign_from_der(&self, der: &[u8]) -> Result<Vec<u8>, ()> {
+		let size = match self.private_key.ec_key() {
+			Ok(ec_key) => (ec_key.group().degree() as i32 + 7) / 8,
+			Err(err) => {
+				eprintln!("Error! Bad EC key: {}", err);
+				return Err(());
+			}
+		};
+		EcdsaSig::from_der(der)
+			.and_then(|sign| {
+				let mut result = sign.r().to_vec_padded(size)?;
+				result.extend(sign.s().to_vec_padded(size)?);
+				Ok(result)
+			})
+			.map_err(|err| {
+				eprintln!("Error! Bad ECDSA sign: {}", err);
+			})
+	}
+}
+
+fn encode_b64url(data: &[u8]) -> String {
+	base64::encode_config(data, base64::URL_SAFE_NO_PAD)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{
+		Public,
+		ras_auth_client::{AccessToken, RasAuthClient, ROLE_SERVICE},
+		ras_key_store::KeyStore,
+	};
+	use openssl::{
+		ec::{EcGroup, EcKey},
+		rsa::Rsa,
+	};
+
+	struct Service {
+		key_store: KeyStore,
+	}
+
+	impl RasAuthClient for Service {
+		fn get_key_store(&self) -> Option<&KeyStore> {
+			Some(&self.key_store)
+		}
+	}
+
+	fn public_key(private_key: &PKey<Private>) -> PKey<Public> {
+		PKey::public_key_from_pem(&private_key.public_key_to_pem().unwrap()).unwrap()
+	}
+
+	#[test]
+	fn signed_token_is_accepted() {
+		let private_key = PKey::from_rsa(Rsa::generate(2048).unwrap()).unwrap();
+		let service = Service {key_store: KeyStore::new()};
+		service.key_store.add_key("key-1", public_key(&private_key));
+		let signer = TokenSigner::new(private_key).set_key_id("key-1");
+		let token_str = signer
+			.sign(&AccessToken::new("service".to_string(), ROLE_SERVICE))
+			.unwrap();
+		let token = service.check_and_get_access_token(&token_str).unwrap();
+		assert_eq!(token.user_name, "service");
+		assert_eq!(token.user_role, ROLE_SERVICE);
+		let jwt = signer.sign_jwt(&serde_json::json!({"sub": "service", "exp": 4102444800_u64})).unwrap();
+		assert_eq!(service.check_and_get_jwt_claims(&jwt).unwrap()["sub"], "service");
+	}
+
+	#[derive(Serialize, serde::Deserialize, Debug, PartialEq)]
+	struct Claims {
+		tenant_id: String,
+		scopes: Vec<String>,
+	}
+
+	struct ClaimsService {
+		key_store: KeyStore,
+	}
+
+	impl RasAuthClient<Claims> for ClaimsService {
+		fn get_key_store(&self) -> Option<&KeyStore> {
+			Some(&self.key_store)
+		}
+	}
+
+	#[test]
+	fn signed_token_with_claims_is_accepted() {
+		let private_key = PKey::from_rsa(Rsa::generate(2048).unwrap()).unwrap();
+		let service = ClaimsService {key_store: KeyStore::new()};
+		service.key_store.add_key("key-1", public_key(&private_key));
+		let claims = Claims {
+			tenant_id: "tenant".to_string(),
+			scopes: vec!["orders:read".to_string()],
+		};
+		let token_str = TokenSigner::new(private_key)
+			.sign(&AccessToken::new_with_claims("user".to_string(), 0, claims))
+			.unwrap();
+		let token: AccessToken<Claims> = service.check_and_get_access_token(&token_str).unwrap();
+		assert_eq!(token.claims.tenant_id, "tenant");
+		assert_eq!(token.claims.scopes, vec!["orders:read".to_string()]);
+		let token: AccessToken = AccessToken::new_from_str(token_str.split("@@").next().unwrap())
+			.unwrap();
+		assert_eq!(token.user_name, "user");
+	}
+
+	#[test]
+	fn jwt_by_ec_and_ed25519_keys() {
+		let group = EcGroup::from_curve_name(Nid::SECP384R1).unwrap();
+		let keys = vec![
+			PKey::from_ec_key(EcKey::generate(&group).unwrap()).unwrap(),
+			PKey::generate_ed25519().unwrap(),
+		];
+		for private_key in keys {
+			let key_store = KeyStore::new();
+			key_store.add_key("key", public_key(&private_key));
+			let jwt = TokenSigner::new(private_key)
+				.set_digest(MessageDigest::sha384())
+				.sign_jwt(&serde_json::json!({"sub": "user", "exp": 4102444800_u64}))
+				.unwrap();
+			assert_eq!(key_store.verify_jwt(&jwt).unwrap()["sub"], "user");
+		}
+	}
+
+	#[test]
+	fn sign_and_verify_by_key_types() {
+		let ec_key = |nid| {
+			let group = EcGroup::from_curve_name(nid).unwrap();
+			PKey::from_ec_key(EcKey::generate(&group).unwrap()).unwrap()
+		};
+		let keys = vec![
+			(PKey::from_rsa(Rsa::generate(2048).unwrap()).unwrap(), MessageDigest::sha256(), true),
+			(ec_key(Nid::X9_62_PRIME256V1), MessageDigest::sha256(), true),
+			(ec_key(Nid::SECP384R1), MessageDigest::sha384(), true),
+			(ec_key(Nid::SECP521R1), MessageDigest::sha512(), true),
+			(PKey::generate_ed25519().unwrap(), MessageDigest::sha256(), false),
+			(PKey::generate_ed448().unwrap(), MessageDigest::sha256(), false),
+		];
+		let claims = serde_json::json!({"sub": "user", "exp": 4102444800_u64});
+		for (private_key, digest, can_sign_token) in keys {
+			let service = Service {key_store: KeyStore::new().set_digest(digest)};
+			service.key_store.add_key("key", public_key(&private_key));
+			let signer = TokenSigner::new(private_key).set_digest(digest).set_key_id("key");
+			let token_str = signer.sign(&AccessToken::new("user".to_string(), 0));
+			assert_eq!(token_str.is_ok(), can_sign_token);
+			if let Ok(token_str) = token_str {
+				let token = service.check_and_get_access_token(&token_str).unwrap();
+				assert_eq!(token.user_name, "user");
+			}
+			let jwt = signer.sign_jwt(&claims).unwrap();
+			assert_eq!(service.check_and_get_jwt_claims(&jwt).unwrap()["sub"], "user");
+		}
+	}
+
+	#[test]
+	fn jws_alg_by_curve_of_key() {
+		let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1).unwrap();
+		let signer = TokenSigner::new(PKey::from_ec_key(EcKey::generate(&group).unwrap()).unwrap());
+		assert_eq!(signer.get_jws_alg(), Ok("ES256".to_string()));
+		let signer = signer.set_digest(MessageDigest::sha384());
+		assert_eq!(signer.get_jws_alg(), Err(()));
+		assert!(signer.sign_jwt(&serde_json::json!({"sub": "user"})).is_err());
+	}
+}
@@ -0,0 +1,409 @@
+use crate::{
+	Arc,
+	Mutex,
+	RequestContext,
+};
+use serde_json::{json, Value};
+use std::{
+	sync::mpsc::{self, Receiver, RecvTimeoutError, SyncSender, TrySendError},
+	time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+/// Flag "sampled" of traceparent
+const FLAG_SAMPLED: u8 = 0x01;
+
+/// Context of trace (W3C Trace Context).
+///
+/// In request it is context of server span: trace_id and flags are taken
+/// from header traceparent (or are generated), span_id is new.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TraceContext {
+	pub trace_id: [u8; 16],
+	pub span_id: [u8; 8],
+	/// Span of caller (from traceparent)
+	pub parent_span_id: Option<[u8; 8]>,
+	pub flags: u8,
+	/// Value of header tracestate, is passed as is
+	pub trace_state: Option<String>,
+}
+
+impl TraceContext {
+	//constructors:
+	/// New trace (sampled)
+	pub fn new_root() -> TraceContext {
+		TraceContext {
+			trace_id: random_id(),
+			span_id: random_id(),
+			parent_span_id: None,
+			flags: FLAG_SAMPLED,
+			trace_state: None,
+		}
+	}
+
+	/// Parse headers traceparent and tracestate, None if traceparent is invalid
+	pub fn from_headers(traceparent: &str, trace_state: Option<&str>) -> Option<TraceContext> {
+		let mut parts = traceparent.trim().split('-');
+		let version = decode_hex::<1>(parts.next()?)?;
+		let trace_id = decode_hex::<16>(parts.next()?)?;
+		let parent_span_id = decode_hex::<8>(parts.next()?)?;
+		let flags = decode_hex::<1>(parts.next()?)?;
+		// future versions can have more fields
+		if version[0] == 0xff || (version[0] == 0 && parts.next().is_some()) {
+			return None;
+		}
+		if trace_id == [0; 16] || parent_span_id == [0; 8] {
+			return None;
+		}
+		Some(TraceContext {
+			trace_id,
+			span_id: random_id(),
+			parent_span_id: Some(parent_span_id),
+			flags: flags[0],
+			trace_state: trace_state.map(|trace_state| trace_state.to_string()),
+		})
+	}
+
+	/// Context of server span of request (child of traceparent or new trace)
+	pub fn from_request(headers: &[(String, String)]) -> TraceContext {
+		let get_header = |name: &str| headers
+			.iter()
+			.find(|(header_name, _)| header_name.eq_ignore_ascii_case(name))
+			.map(|(_, value)| value.as_str());
+		get_header("traceparent")
+			.and_then(|traceparent| TraceContext::from_headers(traceparent, get_header("tracestate")))
+			.unwrap_or_else(TraceContext::new_root)
+	}
+
+	//interface:
+	/// Value of header traceparent for calls to other services
+	pub fn get_traceparent(&self) -> String {
+		format!(
+			"00-{}-{}-{:02x}",
+			self.get_trace_id(),
+			self.get_span_id(),
+			self.flags
+		)
+	}
+
+	pub fn get_trace_id(&self) -> String {
+		encode_hex(&self.trace_id)
+	}
+
+	pub fn get_span_id(&self) -> String {
+		encode_hex(&self.span_id)
+	}
+
+	pub fn is_sampled(&self) -> bool {
+		self.flags & FLAG_SAMPLED != 0
+	}
+}
+
+/// Finished span
+#[derive(Debug, Clone)]
+pub struct Span {
+	pub name: String,
+	pub context: TraceContext,
+	pub start_time: SystemTime,
+	pub end_time: SystemTime,
+	pub attributes: Vec<(String, Value)>,
+	/// Span is failed (status 5xx)
+	pub is_error: bool,
+}
+
+impl Span {
+	/// Span as json line for stdout exporter
+	pub fn to_json(&self) -> Value {
+		json!({
+			"name": self.name,
+			"trace_id": self.context.get_trace_id(),
+			"span_id": self.context.get_span_id(),
+			"parent_span_id": self.context.parent_span_id.map(|span_id| encode_hex(&span_id)),
+			"kind": "server",
+			"start_time_unix_nano": get_unix_nanos(self.start_time),
+			"end_time_unix_nano": get_unix_nanos(self.end_time),
+			"attributes": self.attributes
+				.iter()
+				.cloned()
+				.collect::<serde_json::Map<String, Value>>(),
+			"error": self.is_error,
+		})
+	}
+
+	/// Span in OTLP/JSON format
+	pub fn to_otlp(&self) -> Value {
+		let attributes: Vec<Value> = self.attributes
+			.iter()
+			.map(|(key, value)| json!({"key": key, "value": get_otlp_value(value)}))
+			.collect();
+		let mut span = json!({
+			"traceId": self.context.get_trace_id(),
+			"spanId": self.context.get_span_id(),
+			"name": self.name,
+			// SPAN_KIND_SERVER
+			"kind": 2,
+			"startTimeUnixNano": get_unix_nanos(self.start_time).to_string(),
+			"endTimeUnixNano": get_unix_nanos(self.end_time).to_string(),
+			"attributes": attributes,
+			// STATUS_CODE_ERROR or STATUS_CODE_UNSET
+			"status": {"code": if self.is_error { 2 } else { 0 }},
+		});
+		if let Some(parent_span_id) = self.context.parent_span_id {
+			span["parentSpanId"] = json!(encode_hex(&parent_span_id));
+		}
+		if let Some(ref trace_state) = self.context.trace_state {
+			span["traceState"] = json!(trace_state);
+		}
+		span
+	}
+}
+
+/// Exporter of finished spans, is called in request task (don't block it)
+pub trait SpanExporter: Sync + Send {
+	fn export(&self, span: Span);
+}
+
+/// Exporter, which prints spans as json lines
+pub struct StdoutExporter {}
+
+impl SpanExporter for StdoutExporter {
+	fn export(&self, span: Span) {
+		println!("{}", span.to_json());
+	}
+}
+
+/// Exporter, which keeps spans in memory (for tests)
+#[derive(Default)]
+pub struct InMemoryExporter {
+	spans: Mutex<Vec<Span>>,
+}
+
+impl InMemoryExporter {
+	pub fn new() -> InMemoryExporter {
+		InMemoryExporter::default()
+	}
+
+	pub fn get_spans(&self) -> Vec<Span> {
+		self.spans.lock().unwrap_or_else(|err| err.into_inner()).clone()
+	}
+}
+
+impl SpanExporter for InMemoryExporter {
+	fn export(&self, span: Span) {
+		self.spans.lock().unwrap_or_else(|err| err.into_inner()).push(span);
+	}
+}
+
+/// Exporter by OTLP/HTTP (json) to collector.
+///
+/// Spans are sent by batches in separate thread,
+/// spans beyond queue are dropped.
+///
+/// # Examples
+///
+/// ```ignore
+/// RasServiceBuilder::new(runtime, service)
+///     .set_span_exporter(Arc::new(OtlpExporter::new(
+///         "http://collector:4318/v1/traces",
+///         "orders",
+///     )))
+/// ```
+pub struct OtlpExporter {
+	sender: SyncSender<Span>,
+}
+
+impl OtlpExporter {
+	const QUEUE_SIZE: usize = 2048;
+	const BATCH_SIZE: usize = 512;
+	const FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+
+	/// Endpoint is full url (for example, http://localhost:4318/v1/traces)
+	pub fn new(endpoint: &str, service_name: &str) -> OtlpExporter {
+		let (sender, receiver) = mpsc::sync_channel(OtlpExporter::QUEUE_SIZE);
+		let endpoint = endpoint.to_string();
+		let service_name = service_name.to_string();
+		std::thread::spawn(move || send_batches(receiver, &endpoint, &service_name));
+		OtlpExporter {
+			sender,
+		}
+	}
+}
+
+impl SpanExporter for OtlpExporter {
+	fn export(&self, span: Span) {
+		match self.sender.try_send(span) {
+			Ok(()) => (),
+			Err(TrySendError::Full(_)) => eprintln!("Error! Queue of spans is full, span is dropped"),
+			Err(TrySendError::Disconnected(_)) => eprintln!("Error! Sender of spans is stopped"),
+		}
+	}
+}
+
+fn send_batches(receiver: Receiver<Span>, endpoint: &str, service_name: &str) {
+	let client = reqwest::blocking::Client::new();
+	// first span of batch is waited without timeout
+	while let Ok(span) = receiver.recv() {
+		let mut batch = vec![span];
+		let deadline = Instant::now() + OtlpExporter::FLUSH_INTERVAL;
+		while batch.len() < OtlpExporter::BATCH_SIZE {
+			match receiver.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+				Ok(span) => batch.push(span),
+				Err(RecvTimeoutError::Timeout) => break,
+				Err(RecvTimeoutError::Disconnected) => break,
+			}
+		}
+		let spans: Vec<Value> = batch.iter().map(Span::to_otlp).collect();
+		let body = json!({"resourceSpans": [{
+			"resource": {"attributes": [
+				{"key": "service.name", "value": {"stringValue": service_name}},
+			]},
+			"scopeSpans": [{
+				"scope": {"name": "ras_service"},
+				"spans": spans,
+			}],
+		}]});
+		match client.post(endpoint)
+			.header("Content-Type", "application/json")
+			.body(body.to_string())
+			.send() {
+			Ok(response) if response.status().is_success() => (),
+			Ok(response) => eprintln!("Error! Collector rejected spans: {}", response.status()),
+			Err(err) => eprintln!("Error! Can't send spans: {:?}", err),
+		}
+	}
+}
+
+/// Server span of current request, is exported on finish
+pub(crate) struct ServerSpan {
+	exporter: Arc<dyn SpanExporter>,
+	request: Arc<RequestContext>,
+	context: TraceContext,
+	start_time: SystemTime,
+}
+
+impl ServerSpan {
+	/// Start span, if request is in trace and is sampled
+	pub(crate) fn start(exporter: Option<&Arc<dyn SpanExporter>>) -> Option<ServerSpan> {
+		let exporter = exporter?;
+		let request = RequestContext::current()?;
+		let context = request.trace_context.clone()?;
+		if !context.is_sampled() {
+			return None;
+		}
+		Some(ServerSpan {
+			exporter: exporter.clone(),
+			request,
+			context,
+			start_time: SystemTime::now(),
+		})
+	}
+
+	pub(crate) fn finish(self, status: u16) {
+		let mut attributes = vec![
+			("http.request.method".to_string(), json!(self.request.method)),
+			("http.route".to_string(), json!(self.request.func_name)),
+			("url.path".to_string(), json!(self.request.path)),
+			("http.response.status_code".to_string(), json!(status)),
+		];
+		if let Some(peer_addr) = self.request.peer_addr {
+			attributes.push(("client.address".to_string(), json!(peer_addr.ip().to_string())));
+		}
+		self.exporter.export(Span {
+			name: format!("{} {}", self.request.method, self.request.func_name),
+			context: self.context,
+			start_time: self.start_time,
+			end_time: SystemTime::now(),
+			attributes,
+			is_error: status >= 500,
+		});
+	}
+}
+
+fn get_otlp_value(value: &Value) -> Value {
+	match value {
+		Value::Bool(value) => json!({"boolValue": value}),
+		Value::Number(value) if value.is_i64() || value.is_u64() =>
+			json!({"intValue": value.to_string()}),
+		Value::Number(value) => json!({"doubleValue": value}),
+		Value::String(value) => json!({"stringValue": value}),
+		value => json!({"stringValue": value.to_string()}),
+	}
+}
+
+fn get_unix_nanos(time: SystemTime) -> u128 {
+	time.duration_since(UNIX_EPOCH).unwrap_or_default().as_nanos()
+}
+
+pub(crate) fn random_id<const N: usize>() -> [u8; N] {
+	let mut id = [0; N];
+	while id == [0; N] {
+		if let Err(err) = openssl::rand::rand_bytes(&mut id) {
+			eprintln!("Error! Can't generate id: {:?}", err);
+			let nanos = get_unix_nanos(SystemTime::now()).to_le_bytes();
+			id.iter_mut().zip(nanos.iter().cycle()).for_each(|(byte, nano)| *byte = *nano);
+		}
+	}
+	id
+}
+
+fn encode_hex(bytes: &[u8]) -> String {
+	bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// Decode lowercase hex of exactly N bytes
+fn decode_hex<const N: usize>(hex: &str) -> Option<[u8; N]> {
+	if hex.len() != N * 2 || !hex.bytes().all(|char| matches!(char, b'0'..=b'9' | b'a'..=b'f')) {
+		return None;
+	}
+	let mut bytes = [0; N];
+	for (index, byte) in bytes.iter_mut().enumerate() {
+		*byte = u8::from_str_radix(&hex[index * 2..index * 2 + 2], 16).ok()?;
+	}
+	Some(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn traceparent_is_continued() {
+		let headers = vec![
+			(
+				"Traceparent".to_string(),
+				"00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01".to_string()
+			),
+			("tracestate".to_string(), "vendor=value".to_string()),
+		];
+		let context = TraceContext::from_request(&headers);
+		assert_eq!(context.get_trace_id(), "4bf92f3577b34da6a3ce929d0e0e4736");
+		assert_eq!(context.parent_span_id, Some([0x00, 0xf0, 0x67, 0xaa, 0x0b, 0xa9, 0x02, 0xb7]));
+		assert_ne!(context.get_span_id(), "00f067aa0ba902b7");
+		assert_eq!(context.trace_state.as_deref(), Some("vendor=value"));
+		assert!(context.is_sampled());
+		assert!(context.get_traceparent().starts_with("00-4bf92f3577b34da6a3ce929d0e0e4736-"));
+		for traceparent in [
+			"00-00000000000000000000000000000000-00f067aa0ba902b7-01",
+			"00-4BF92F3577B34DA6A3CE929D0E0E4736-00f067aa0ba902b7-01",
+			"ff-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01",
+			"00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7",
+		] {
+			assert_eq!(TraceContext::from_headers(traceparent, None), None);
+		}
+		let root = TraceContext::from_request(&[]);
+		assert_eq!(root.parent_span_id, None);
+		assert!(root.is_sampled());
+
+		let span = Span {
+			name: "GET orders".to_string(),
+			context,
+			start_time: UNIX_EPOCH,
+			end_time: UNIX_EPOCH + Duration::from_millis(5),
+			attributes: vec![("http.response.status_code".to_string(), json!(200))],
+			is_error: false,
+		};
+		let otlp = span.to_otlp();
+		assert_eq!(otlp["parentSpanId"], "00f067aa0ba902b7");
+		assert_eq!(otlp["endTimeUnixNano"], "5000000");
+		assert_eq!(otlp["attributes"][0]["value"]["intValue"], "200");
+	}
+}